@@ -1,5 +1,8 @@
-use miette::{Diagnostic, Error, SourceSpan};
+use miette::{
+    Diagnostic, Error, MietteError, MietteSpanContents, SourceCode, SourceSpan, SpanContents,
+};
 use std::fmt;
+use std::ops::{Deref, Range};
 use thiserror::Error;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -63,6 +66,28 @@ impl Atomic {
         }
     }
 
+    /// The q type of an atom of this kind, i.e. what `type` gives for the literal, without
+    /// building its `Q` value
+    pub fn type_id(&self) -> i8 {
+        match self {
+            Self::Boolean => -1,
+            Self::Byte => -4,
+            Self::Short => -5,
+            Self::Int => -6,
+            Self::Long => -7,
+            Self::Real => -8,
+            Self::Float => -9,
+            Self::Char => -10,
+            Self::Symbol => -11,
+            Self::Timestamp => -12,
+            Self::Month => -13,
+            Self::Date => -14,
+            Self::Timespan => -16,
+            Self::Minute => -17,
+            Self::Second => -18,
+        }
+    }
+
     /// Parse untyped literals. Valid forms:
     /// - long      : `42` (digits only)
     /// - float     : `4.2` (digits.digits)
@@ -109,14 +134,24 @@ impl Atomic {
                 (2, true) => Some(Self::Timespan),
                 _ => None,
             }
-        } else if dot_count > 0 {
-            if dot_count == 2 {
-                Some(Self::Date)
-            } else if dot_count == 1 {
-                Some(Self::Float)
-            } else {
-                None
+        } else if dot_count == 2 {
+            let parts: Vec<&str> = origin.split('.').collect();
+            let is_date = parts.iter().map(|p| p.len()).eq([4, 2, 2])
+                && parts.iter().all(|p| p.bytes().all(|b| b.is_ascii_digit()));
+            if !is_date {
+                return Err(InvalidLiteralError::new(
+                    src,
+                    origin,
+                    "a number has at most one decimal point",
+                    offset..offset + origin.len(),
+                    Some("dates are written YYYY.MM.DD"),
+                ));
             }
+            Some(Self::Date)
+        } else if dot_count == 1 || origin.contains(['e', 'E']) {
+            Some(Self::Float)
+        } else if dot_count > 2 {
+            None
         } else {
             Some(Self::Long)
         };
@@ -133,11 +168,85 @@ impl Atomic {
     }
 }
 
+/// The source text of a diagnostic, with the name of the file it was read from if there is one
+#[derive(Debug, Clone)]
+pub struct Source {
+    text: String,
+    filename: Option<String>,
+}
+
+impl Source {
+    pub fn new(text: &str) -> Self {
+        Self {
+            text: text.to_string(),
+            filename: None,
+        }
+    }
+
+    pub fn filename(&self) -> Option<&str> {
+        self.filename.as_deref()
+    }
+
+    pub(crate) fn set_filename(&mut self, filename: &str) {
+        self.filename = Some(filename.to_string());
+    }
+}
+
+impl Deref for Source {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.text
+    }
+}
+
+impl SourceCode for Source {
+    fn read_span<'a>(
+        &'a self,
+        span: &SourceSpan,
+        context_lines_before: usize,
+        context_lines_after: usize,
+    ) -> Result<Box<dyn SpanContents<'a> + 'a>, MietteError> {
+        let contents = self
+            .text
+            .read_span(span, context_lines_before, context_lines_after)?;
+        let Some(filename) = &self.filename else {
+            return Ok(contents);
+        };
+        Ok(Box::new(MietteSpanContents::new_named(
+            filename.clone(),
+            contents.data(),
+            *contents.span(),
+            contents.line(),
+            contents.column(),
+            contents.line_count(),
+        )))
+    }
+}
+
+/// Names the file behind the source of a lexer diagnostic
+pub(crate) fn with_filename(mut err: Error, filename: Option<&str>) -> Error {
+    let Some(filename) = filename else {
+        return err;
+    };
+    let src = if let Some(e) = err.downcast_mut::<InvalidLiteralError>() {
+        &mut e.src
+    } else if let Some(e) = err.downcast_mut::<SingleTokenError>() {
+        &mut e.src
+    } else if let Some(e) = err.downcast_mut::<StringTerminationError>() {
+        &mut e.src
+    } else {
+        return err;
+    };
+    src.set_filename(filename);
+    err
+}
+
 #[derive(Diagnostic, Debug, Error)]
 #[error("Invalid literal '{literal}' because {reason}")]
 pub struct InvalidLiteralError {
     #[source_code]
-    pub src: String,
+    pub src: Source,
 
     pub literal: String,
 
@@ -159,9 +268,9 @@ impl InvalidLiteralError {
         help: Option<&'static str>,
     ) -> Self {
         Self {
-            src: src.to_string(),
+            src: Source::new(src),
             literal: literal.to_string(),
-            reason: reason,
+            reason,
             err_span: range.into(),
             help,
         }
@@ -177,7 +286,7 @@ impl InvalidLiteralError {
 #[error("Unexpected token '{token}'")]
 pub struct SingleTokenError {
     #[source_code]
-    src: String,
+    src: Source,
 
     pub token: char,
 
@@ -199,7 +308,7 @@ impl SingleTokenError {
 #[error("Unterminated string")]
 pub struct StringTerminationError {
     #[source_code]
-    src: String,
+    src: Source,
 
     #[label = "this string literal"]
     err_span: SourceSpan,
@@ -219,6 +328,34 @@ pub struct Token<'de> {
     pub kind: TokenKind,
 }
 
+impl Token<'_> {
+    /// The bytes of a string literal token, with the quotes stripped and escapes decoded,
+    /// e.g. `"ab\nc"` gives `a`, `b`, a line feed and `c`
+    pub fn as_q_string(&self) -> Option<Vec<u8>> {
+        if !matches!(
+            self.kind,
+            TokenKind::Single(Atomic::Char) | TokenKind::Vector(Atomic::Char)
+        ) {
+            return None;
+        }
+        let body = self.origin.strip_prefix('"')?.strip_suffix('"')?;
+        Some(unescape(body))
+    }
+
+    /// Whether the token names something in a namespace reserved for q itself, i.e. one with
+    /// a single-letter name such as `.z.p`, `.Q.fs` or `.h`
+    pub fn is_builtin_namespace(&self) -> bool {
+        if self.kind != TokenKind::Identifier {
+            return false;
+        }
+        let Some(name) = self.origin.strip_prefix('.') else {
+            return false;
+        };
+        let namespace = name.split('.').next().unwrap_or(name);
+        namespace.len() == 1 && namespace.starts_with(|c: char| c.is_ascii_alphabetic())
+    }
+}
+
 impl fmt::Display for Token<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "kind={:?}, origin={}", self.kind, self.origin)
@@ -320,6 +457,8 @@ pub enum TokenKind {
     BackslashColon,     // \: each left
     BackslashBackslash, // \\ abort
     AssignThrough(AssignThrough),
+    FileOp(u8), // 0: 1: 2:
+    Show,       // 0N!
 
     // Literals.
     Identifier,
@@ -336,6 +475,7 @@ pub struct Lexer<'de> {
     rest: &'de str,
     byte: usize,
     peeked: Option<Result<Token<'de>, miette::Error>>,
+    filename: Option<String>,
 }
 
 impl<'de> Lexer<'de> {
@@ -345,6 +485,15 @@ impl<'de> Lexer<'de> {
             rest: input,
             byte: 0,
             peeked: None,
+            filename: None,
+        }
+    }
+
+    /// A lexer over the contents of the file `filename`, which its diagnostics name
+    pub fn new_named(input: &'de str, filename: &str) -> Self {
+        Self {
+            filename: Some(filename.to_string()),
+            ..Self::new(input)
         }
     }
 }
@@ -368,7 +517,30 @@ impl<'de> Iterator for Lexer<'de> {
         if let Some(next) = self.peeked.take() {
             return Some(next);
         }
+        let next = self.scan()?;
+        Some(next.map_err(|e| with_filename(e, self.filename.as_deref())))
+    }
+}
+
+impl<'de> Lexer<'de> {
+    // Helper methods
+    /// The two-char token `kind` starting at `c_at`, whose first char has been consumed
+    fn pair(
+        &mut self,
+        kind: TokenKind,
+        c_onwards: &'de str,
+        c_at: usize,
+    ) -> Option<Result<Token<'de>, Error>> {
+        self.rest = &self.rest[1..];
+        self.byte += 1;
+        Some(Ok(Token {
+            origin: &c_onwards[..2],
+            offset: c_at,
+            kind,
+        }))
+    }
 
+    fn scan(&mut self) -> Option<Result<Token<'de>, Error>> {
         loop {
             let mut chars = self.rest.chars(); // iterator to unparsed chars
             let c = chars.next()?; // current char
@@ -406,47 +578,47 @@ impl<'de> Iterator for Lexer<'de> {
                 '[' => return just(TokenKind::LeftBracket),
                 ']' => return just(TokenKind::RightBracket),
                 ';' => return just(TokenKind::Semicolon),
+                // a minus is the sign of a number literal, e.g. `-0W` or the `-2` in `1 -2`,
+                // unless it directly follows a noun as in `x-1` or `1-2`. Temporal literals
+                // take their sign the same way, so `-2D00:00:00` is one timespan token.
+                '-' if starts_number(self.rest)
+                    && (c_at == 0 || !ends_noun(&self.whole[..c_at])) =>
+                {
+                    Started::Number(self.rest.chars().next().unwrap().to_digit(10).unwrap_or(0))
+                }
+                // a leading dot starts a float, e.g. `.5`, while `x.5` stays `x` `.` `5`
+                '.' if self.rest.starts_with(|c: char| c.is_ascii_digit())
+                    && (c_at == 0 || !ends_noun(&self.whole[..c_at])) =>
+                {
+                    Started::Number(0)
+                }
+                // a leading dot starts a namespaced name, e.g. `.z.p`, while `a.b` stays
+                // `a` `.` `b`
+                '.' if self.rest.starts_with(|c: char| c.is_ascii_alphabetic())
+                    && (c_at == 0 || !ends_noun(&self.whole[..c_at])) =>
+                {
+                    Started::Identifier
+                }
                 c @ ('.' | '@' | '$' | '!' | '?' | '+' | '-' | '*' | '%' | '=' | '~' | '<'
                 | '>' | '|' | '&' | '#' | '_' | '^' | ',') => {
                     // These chars can be assign through operator tokens
                     if self.rest.starts_with(':') {
-                        self.rest = &self.rest[1..];
-                        self.byte += 1;
-                        let assign_through = AssignThrough::from_char(c).unwrap();
-                        return Some(Ok(Token {
-                            origin: &c_onwards[..2],
-                            offset: c_at,
-                            kind: TokenKind::AssignThrough(assign_through),
-                        }));
+                        return self.pair(
+                            TokenKind::AssignThrough(AssignThrough::from_char(c).unwrap()),
+                            c_onwards,
+                            c_at,
+                        );
                     }
                     // Handle two-chars tokens: <>, >=, <=
                     let kind = match c {
                         '<' if self.rest.starts_with('>') => {
-                            self.rest = &self.rest[1..];
-                            self.byte += 1;
-                            return Some(Ok(Token {
-                                origin: &c_onwards[..2],
-                                offset: c_at,
-                                kind: TokenKind::NotEqual,
-                            }));
+                            return self.pair(TokenKind::NotEqual, c_onwards, c_at);
                         }
                         '<' if self.rest.starts_with('=') => {
-                            self.rest = &self.rest[1..];
-                            self.byte += 1;
-                            return Some(Ok(Token {
-                                origin: &c_onwards[..2],
-                                offset: c_at,
-                                kind: TokenKind::LessEqual,
-                            }));
+                            return self.pair(TokenKind::LessEqual, c_onwards, c_at);
                         }
                         '>' if self.rest.starts_with('=') => {
-                            self.rest = &self.rest[1..];
-                            self.byte += 1;
-                            return Some(Ok(Token {
-                                origin: &c_onwards[..2],
-                                offset: c_at,
-                                kind: TokenKind::GreaterEqual,
-                            }));
+                            return self.pair(TokenKind::GreaterEqual, c_onwards, c_at);
                         }
                         '.' => TokenKind::Dot,
                         '@' => TokenKind::At,
@@ -473,45 +645,21 @@ impl<'de> Iterator for Lexer<'de> {
                 }
                 ':' => {
                     if self.rest.starts_with(':') {
-                        self.rest = &self.rest[1..];
-                        self.byte += 1;
-                        return Some(Ok(Token {
-                            origin: &c_onwards[..2],
-                            offset: c_at,
-                            kind: TokenKind::ColonColon,
-                        }));
+                        return self.pair(TokenKind::ColonColon, c_onwards, c_at);
                     }
                     return just(TokenKind::Colon);
                 }
                 '\'' => {
                     if self.rest.starts_with(':') {
-                        self.rest = &self.rest[1..];
-                        self.byte += 1;
-                        return Some(Ok(Token {
-                            origin: &c_onwards[..2],
-                            offset: c_at,
-                            kind: TokenKind::QuoteColon,
-                        }));
+                        return self.pair(TokenKind::QuoteColon, c_onwards, c_at);
                     }
                     return just(TokenKind::Quote);
                 }
                 '\\' => {
                     if self.rest.starts_with(':') {
-                        self.rest = &self.rest[1..];
-                        self.byte += 1;
-                        return Some(Ok(Token {
-                            origin: &c_onwards[..2],
-                            offset: c_at,
-                            kind: TokenKind::BackslashColon,
-                        }));
+                        return self.pair(TokenKind::BackslashColon, c_onwards, c_at);
                     } else if self.rest.starts_with('\\') {
-                        self.rest = &self.rest[1..];
-                        self.byte += 1;
-                        return Some(Ok(Token {
-                            origin: &c_onwards[..2],
-                            offset: c_at,
-                            kind: TokenKind::BackslashBackslash,
-                        }));
+                        return self.pair(TokenKind::BackslashBackslash, c_onwards, c_at);
                     }
                     return just(TokenKind::BackSlash);
                 }
@@ -523,7 +671,7 @@ impl<'de> Iterator for Lexer<'de> {
                 c if c.is_whitespace() => continue,
                 c => {
                     return Some(Err(SingleTokenError {
-                        src: self.whole.to_string(),
+                        src: Source::new(self.whole),
                         token: c,
                         err_span: SourceSpan::from(self.byte - c.len_utf8()..self.byte),
                         help: None,
@@ -552,10 +700,19 @@ impl<'de> Iterator for Lexer<'de> {
                     // q)type x
                     // 108h
                     // ```
+                    // a symbol holds letters, digits, `_`, `.` and `:`, e.g. `.ns.fn`, and a file
+                    // handle such as `:data/t.csv` also `/`
+                    let mut segment_start = true;
+                    let mut handle = false;
                     let end = self
                         .rest
                         .find(|c: char| {
-                            !c.is_ascii_alphanumeric() && c != '_' && c != ':' && c != '`'
+                            if segment_start {
+                                handle = c == ':';
+                            }
+                            segment_start = c == '`';
+                            let name = c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | ':');
+                            !(name || c == '`' || (handle && c == '/'))
                         })
                         .unwrap_or(self.rest.len());
 
@@ -566,7 +723,7 @@ impl<'de> Iterator for Lexer<'de> {
                         // q) `a`_b / legal
                         let c = self.rest.chars().next().unwrap();
                         let err = SingleTokenError {
-                            src: self.whole.to_string(),
+                            src: Source::new(self.whole),
                             token: c,
                             err_span: SourceSpan::from(self.byte..self.byte + c.len_utf8()),
                             help: Some(
@@ -578,12 +735,12 @@ impl<'de> Iterator for Lexer<'de> {
                         return Some(Err(err.into()));
                     }
 
-                    //TODO: if a symbol starts with :, it will be parsed as a path, therefore / is allowed
-
                     let literal = &c_onwards[..end + 1];
                     self.byte += end;
                     self.rest = &self.rest[end..];
 
+                    // a run of adjacent symbols is one vector token, e.g. `x`y`, which the
+                    // parser splits on its backticks, while a lone `x stays single
                     let token_kind = if tag < end {
                         TokenKind::Vector(Atomic::Symbol)
                     } else {
@@ -609,10 +766,12 @@ impl<'de> Iterator for Lexer<'de> {
                         }
                     });
                     if let Some(end) = end {
+                        // adjacent strings are separate literals, e.g. `"ab""cd"` is two
                         let literal = &c_onwards[..end + 1 + 1];
                         self.byte += end + 1;
                         self.rest = &self.rest[end + 1..];
-                        let token_kind = if end == 1 {
+                        // a single char may be escaped, e.g. `"\""`
+                        let token_kind = if unescape(&literal[1..=end]).len() == 1 {
                             TokenKind::Single(Atomic::Char)
                         } else {
                             TokenKind::Vector(Atomic::Char)
@@ -624,7 +783,7 @@ impl<'de> Iterator for Lexer<'de> {
                         }))
                     } else {
                         let err = StringTerminationError {
-                            src: self.whole.to_string(),
+                            src: Source::new(self.whole),
                             err_span: SourceSpan::from(self.byte - c.len_utf8()..self.whole.len()),
                         };
 
@@ -637,29 +796,24 @@ impl<'de> Iterator for Lexer<'de> {
                 }
 
                 Started::Slash => {
-                    // TODO:
-                    // 1. a slash is also valid for a comment when it's at the beginning of one file
-                    // 2. support multi-line comments
-                    if prev_whitespace {
-                        let line_end = self.rest.find('\n').unwrap_or(self.rest.len());
-                        let comment_closed = self.rest.find('\\');
-
-                        let offset = if let Some(comment_closed) = comment_closed {
-                            comment_closed + 1
-                        } else {
-                            line_end
-                        };
+                    // a slash at the start of a line or after whitespace comments out the rest
+                    // of the line, e.g. `1 2 / two longs`, while `2/3` and `+/` stay operators.
+                    // A slash alone on its line opens a block comment, up to a backslash alone
+                    // on its line or the end of input.
+                    let line_start =
+                        c_at == 0 || matches!(self.whole.as_bytes()[c_at - 1], b'\n' | b'\r');
+                    if line_start && self.rest[..line_end(self.rest)].trim().is_empty() {
+                        let offset = block_comment_end(self.rest);
+                        self.byte += offset;
+                        self.rest = &self.rest[offset..];
+                        continue;
+                    } else if prev_whitespace {
+                        let offset = line_end(self.rest);
                         self.byte += offset;
                         self.rest = &self.rest[offset..];
                         continue;
                     } else if self.rest.starts_with(':') {
-                        self.rest = &self.rest[1..];
-                        self.byte += 1;
-                        Some(Ok(Token {
-                            origin: &c_onwards[..2],
-                            offset: c_at,
-                            kind: TokenKind::SlashColon,
-                        }))
+                        self.pair(TokenKind::SlashColon, c_onwards, c_at)
                     } else {
                         Some(Ok(Token {
                             origin: c_str,
@@ -669,10 +823,24 @@ impl<'de> Iterator for Lexer<'de> {
                     }
                 }
                 Started::Identifier => {
-                    // TODO: dot is also allowed, which directly creat a dictionary
-                    let first_non_ident = c_onwards
-                        .find(|c| !matches!(c, 'a'..='z' | 'A'..='Z' | '0'..='9' | '_'))
-                        .unwrap_or(c_onwards.len());
+                    // a name is a letter then letters, digits and underscores, e.g. `a1_b`.
+                    // Only a leading dot makes a dotted name one token, e.g. `.ns.fn`, so `a.b`
+                    // is `a` `.` `b`. As in q, `_foo` is the `_` verb applied to `foo`.
+                    let is_ident = |c| matches!(c, 'a'..='z' | 'A'..='Z' | '0'..='9' | '_');
+                    let first_non_ident = if c == '.' {
+                        let mut end = 0;
+                        while c_onwards[end..].starts_with('.')
+                            && c_onwards[end + 1..].starts_with(|c: char| c.is_ascii_alphabetic())
+                        {
+                            end += 1;
+                            end += c_onwards[end..]
+                                .find(|c| !is_ident(c))
+                                .unwrap_or(c_onwards.len() - end);
+                        }
+                        end
+                    } else {
+                        c_onwards.find(|c| !is_ident(c)).unwrap_or(c_onwards.len())
+                    };
 
                     let literal = &c_onwards[..first_non_ident];
                     let extra_bytes = literal.len() - c.len_utf8();
@@ -686,6 +854,23 @@ impl<'de> Iterator for Lexer<'de> {
                     }))
                 }
                 Started::Number(n) => {
+                    // 0: 1: 2: are file/prepare operators, only when the colon is not
+                    // followed by a digit (`1:30` stays a temporal literal)
+                    if n <= 2
+                        && self.rest.starts_with(':')
+                        && !self.rest[1..].starts_with(|c: char| c.is_ascii_digit() || c == ':')
+                    {
+                        return self.pair(TokenKind::FileOp(n as u8), c_onwards, c_at);
+                    }
+                    if c_onwards.starts_with("0N!") && !self.rest[2..].starts_with(':') {
+                        self.rest = &self.rest[2..];
+                        self.byte += 2;
+                        return Some(Ok(Token {
+                            origin: &c_onwards[..3],
+                            offset: c_at,
+                            kind: TokenKind::Show,
+                        }));
+                    }
                     if n == 0 && self.rest.starts_with('x') {
                         let after_0x = &c_onwards[2..]; // skip "0x"
                         let hex_len = after_0x
@@ -693,6 +878,39 @@ impl<'de> Iterator for Lexer<'de> {
                             .unwrap_or(after_0x.len());
                         let first_non_digit = 2 + hex_len;
                         let literal = &c_onwards[..first_non_digit];
+                        // `0x` always denotes bytes, so a suffix like the `j` in `0x0aj` does
+                        // not make a hex long
+                        let suffix_len = c_onwards[first_non_digit..]
+                            .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                            .unwrap_or(c_onwards.len() - first_non_digit);
+                        if suffix_len > 0 {
+                            let end = first_non_digit + suffix_len;
+                            self.byte += end - c.len_utf8();
+                            self.rest = &c_onwards[end..];
+                            return Some(Err(InvalidLiteralError::new(
+                                self.whole,
+                                &c_onwards[..end],
+                                "a byte literal takes no type suffix",
+                                c_at..c_at + end,
+                                Some("`0x` always denotes bytes; write other integers in decimal"),
+                            )
+                            .into()));
+                        }
+
+                        // a lone digit is padded as in q, `0xf` is `0x0f`, but in a longer odd
+                        // run it is unclear which byte the extra digit belongs to
+                        if hex_len > 1 && hex_len % 2 == 1 {
+                            self.byte += first_non_digit - c.len_utf8();
+                            self.rest = &c_onwards[first_non_digit..];
+                            return Some(Err(InvalidLiteralError::new(
+                                self.whole,
+                                literal,
+                                "a byte literal needs an even number of hex digits",
+                                c_at..c_at + first_non_digit,
+                                Some("each byte is two hex digits, e.g. `0x0abc`"),
+                            )
+                            .into()));
+                        }
 
                         let extra_bytes = literal.len() - c.len_utf8();
                         self.byte += extra_bytes;
@@ -711,7 +929,14 @@ impl<'de> Iterator for Lexer<'de> {
                     // TODO: 1 2. 3 is a valid float vector literal
                     // TODO: a leading D is a valid timespan literal! (but very bizarre)
                     } else {
-                        let (lpos, rpos, mut is_single_token) = find_num_end(c_onwards);
+                        let (lpos, mut rpos, mut is_single_token) = find_num_end(c_onwards);
+                        // `n` is scanned as part of the float null `0n`, anywhere else it is the
+                        // timespan suffix, e.g. `0Wn` `12:34:56n`
+                        if c_onwards[..rpos].ends_with('n')
+                            && c_onwards[lpos..rpos].trim_start_matches('-') != "0n"
+                        {
+                            rpos -= 1;
+                        }
                         let suffix = c_onwards[rpos..].chars().next().unwrap_or('\0');
 
                         let (literal, num_type) =
@@ -724,6 +949,8 @@ impl<'de> Iterator for Lexer<'de> {
                                         digits.find(|c: char| c != '0' && c != '1')
                                     {
                                         let invalid_offset = c_at + invalid_pos;
+                                        self.byte += literal.len() - c.len_utf8();
+                                        self.rest = &c_onwards[literal.len()..];
                                         return Some(Err(InvalidLiteralError::new(
                                             self.whole,
                                             literal,
@@ -739,11 +966,7 @@ impl<'de> Iterator for Lexer<'de> {
                                 (literal, num_type)
                             } else {
                                 let literal = &c_onwards[..rpos];
-                                let num_type = match Atomic::parse_untyped(
-                                    &c_onwards[lpos..rpos],
-                                    c_at,
-                                    self.whole,
-                                ) {
+                                let num_type = match untyped_type(literal, c_at, self.whole) {
                                     Ok(t) => t,
                                     Err(e) => return Some(Err(e.into())),
                                 };
@@ -754,6 +977,20 @@ impl<'de> Iterator for Lexer<'de> {
                         self.byte += extra_bytes;
                         self.rest = &self.rest[extra_bytes..];
 
+                        if let Some(item) = out_of_range_item(&c_onwards[..rpos], num_type) {
+                            let item_offset = c_at + item.start;
+                            return Some(Err(InvalidLiteralError::new(
+                                self.whole,
+                                &c_onwards[item.clone()],
+                                "it is out of range for its type",
+                                item_offset..item_offset + item.len(),
+                                Some(
+                                    "shorts, ints and longs are 16, 32 and 64-bit signed integers",
+                                ),
+                            )
+                            .into()));
+                        }
+
                         let token_kind = if is_single_token {
                             TokenKind::Single(num_type)
                         } else {
@@ -772,15 +1009,149 @@ impl<'de> Iterator for Lexer<'de> {
     }
 }
 
+/// Decode the body of a string literal, e.g. `a\"b\n` -> `a"b<LF>`
+pub(crate) fn unescape(body: &str) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(body.len());
+    let mut iter = body.bytes().peekable();
+    while let Some(b) = iter.next() {
+        if b != b'\\' {
+            bytes.push(b);
+            continue;
+        }
+        match iter.next() {
+            Some(b'n') => bytes.push(b'\n'),
+            Some(b't') => bytes.push(b'\t'),
+            Some(b'r') => bytes.push(b'\r'),
+            Some(d @ b'0'..=b'7') => {
+                // octal escape \ddd
+                let mut value = (d - b'0') as u32;
+                for _ in 0..2 {
+                    match iter.peek() {
+                        Some(&d @ b'0'..=b'7') => {
+                            value = value * 8 + (d - b'0') as u32;
+                            iter.next();
+                        }
+                        _ => break,
+                    }
+                }
+                bytes.push(value as u8);
+            }
+            Some(other) => bytes.push(other),
+            None => bytes.push(b'\\'),
+        }
+    }
+    bytes
+}
+
+/// Whether `before` ends with a char that closes a noun, so a minus right after it subtracts
+fn ends_noun(before: &str) -> bool {
+    before.chars().next_back().is_some_and(|c| {
+        c.is_ascii_alphanumeric() || matches!(c, ')' | ']' | '}' | '"' | '_' | '.')
+    })
+}
+
+/// Byte index of the end of the line closing a block comment opened just before `s`, i.e. the
+/// next line holding only a backslash, or the length of `s` if the comment is never closed
+fn block_comment_end(s: &str) -> usize {
+    let mut pos = line_end(s);
+    while pos < s.len() {
+        pos += if s[pos..].starts_with("\r\n") { 2 } else { 1 };
+        let end = pos + line_end(&s[pos..]);
+        if s[pos..end].trim_end() == "\\" {
+            return end;
+        }
+        pos = end;
+    }
+    s.len()
+}
+
+/// Byte index of the first line break in `s` (or its length), treating `\n`, `\r\n` and a lone
+/// `\r` alike so that scripts saved on Windows lex the same
+pub(crate) fn line_end(s: &str) -> usize {
+    s.find(['\n', '\r']).unwrap_or(s.len())
+}
+
+/// The byte range of the first plain decimal item of a short, int or long literal that does
+/// not fit its type, e.g. the `40000` of `1 40000h`
+fn out_of_range_item(digits: &str, num_type: Atomic) -> Option<Range<usize>> {
+    let fits = |item: &str| match num_type {
+        Atomic::Short => item.parse::<i16>().is_ok(),
+        Atomic::Int => item.parse::<i32>().is_ok(),
+        Atomic::Long => item.parse::<i64>().is_ok(),
+        _ => true,
+    };
+    let mut start = 0;
+    for item in digits.split(' ') {
+        let plain = !item.is_empty()
+            && item
+                .strip_prefix('-')
+                .unwrap_or(item)
+                .bytes()
+                .all(|b| b.is_ascii_digit());
+        if plain && !fits(item) {
+            return Some(start..start + item.len());
+        }
+        start += item.len() + 1;
+    }
+    None
+}
+
+/// The type of an unsuffixed number literal from its items, which share one type except that
+/// longs and floats mix into floats, e.g. `1.5 2`. Nulls and infinities such as `0N` take the
+/// type of the other items.
+fn untyped_type(literal: &str, offset: usize, src: &str) -> Result<Atomic, InvalidLiteralError> {
+    let mut types = Vec::new();
+    let mut start = 0;
+    for item in literal.split(' ') {
+        let special = matches!(item.trim_start_matches('-'), "0N" | "0W" | "0n" | "0w");
+        if !item.is_empty() && !special {
+            types.push(Atomic::parse_untyped(item, offset + start, src)?);
+        }
+        start += item.len() + 1;
+    }
+    let Some(&last) = types.last() else {
+        let last = literal.rsplit(' ').next().unwrap_or(literal);
+        return Atomic::parse_untyped(last, offset + literal.len() - last.len(), src);
+    };
+    let mixes_into_float = types.contains(&Atomic::Float)
+        && types
+            .iter()
+            .all(|t| matches!(t, Atomic::Long | Atomic::Float));
+    Ok(if mixes_into_float {
+        Atomic::Float
+    } else {
+        last
+    })
+}
+
+/// Whether `s` starts with a number, e.g. `5` or `.5`
+fn starts_number(s: &str) -> bool {
+    let s = s.strip_prefix('.').unwrap_or(s);
+    s.starts_with(|c: char| c.is_ascii_digit())
+}
+
 fn find_num_end(c_onwards: &str) -> (usize, usize, bool) {
     let mut lpos = 0;
     let mut rpos = 0;
     let mut is_single_token = true;
 
     while rpos < c_onwards.len() {
+        if c_onwards[rpos..].starts_with('-') {
+            rpos += 1;
+        }
         rpos += c_onwards[rpos..]
             .find(|c| !matches!(c, '.' | ':' | 'D' | 'N' | 'W' | 'n' | 'w' | '0'..='9'))
             .unwrap_or(c_onwards.len() - rpos);
+        // an exponent, e.g. `1e6` `2.5e-3`, unlike the real suffix of `2.5e`
+        if let Some(exponent) = c_onwards[rpos..].strip_prefix(['e', 'E']) {
+            let digits = exponent.strip_prefix('-').unwrap_or(exponent);
+            if digits.starts_with(|c: char| c.is_ascii_digit()) {
+                let digits_end = digits
+                    .find(|c: char| !c.is_ascii_digit())
+                    .unwrap_or(digits.len());
+                rpos = c_onwards.len() - digits.len() + digits_end;
+            }
+        }
 
         let space_start = rpos;
         // only accept ascii space char (U+0020)
@@ -788,7 +1159,11 @@ fn find_num_end(c_onwards: &str) -> (usize, usize, bool) {
             rpos += 1;
         }
 
-        if rpos < c_onwards.len() && c_onwards.as_bytes()[rpos].is_ascii_digit() {
+        // a negative item continues the vector, e.g. `1 -2`
+        let item = c_onwards[rpos..]
+            .strip_prefix('-')
+            .unwrap_or(&c_onwards[rpos..]);
+        if rpos > space_start && starts_number(item) {
             lpos = rpos;
             is_single_token = false;
         } else {
@@ -800,3 +1175,377 @@ fn find_num_end(c_onwards: &str) -> (usize, usize, bool) {
     // if suffixed, rpos would be the byte index of the suffix char
     (lpos, rpos, is_single_token)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::qtype::Q;
+    use crate::qtype::symbol::Symbol;
+
+    fn kinds(input: &str) -> Vec<TokenKind> {
+        Lexer::new(input).map(|token| token.unwrap().kind).collect()
+    }
+
+    fn origins(input: &str) -> Vec<&str> {
+        Lexer::new(input)
+            .map(|token| token.unwrap().origin)
+            .collect()
+    }
+
+    #[test]
+    fn file_ops_and_show() {
+        assert_eq!(
+            kinds("`:t.csv 0: x"),
+            [
+                TokenKind::Single(Atomic::Symbol),
+                TokenKind::FileOp(0),
+                TokenKind::Identifier
+            ]
+        );
+        assert_eq!(kinds("1: 2:"), [TokenKind::FileOp(1), TokenKind::FileOp(2)]);
+        assert_eq!(
+            kinds("0 : 5"),
+            [
+                TokenKind::Single(Atomic::Long),
+                TokenKind::Colon,
+                TokenKind::Single(Atomic::Long)
+            ]
+        );
+        assert_eq!(kinds("0N!x"), [TokenKind::Show, TokenKind::Identifier]);
+    }
+
+    #[test]
+    fn crlf_line_endings_end_comments() {
+        assert_eq!(origins("1 / one\r\n/ a line\r\n2"), ["1", "2"]);
+        assert_eq!(origins("x\r\n/\r\nblock 1\r\n\\\r\ny"), ["x", "y"]);
+        assert_eq!(origins("a\r/ old mac line\rb"), ["a", "b"]);
+    }
+
+    #[test]
+    fn atomic_type_ids_match_their_values() {
+        let samples = [
+            (Atomic::Boolean, "1b"),
+            (Atomic::Byte, "0x2a"),
+            (Atomic::Short, "42h"),
+            (Atomic::Int, "42i"),
+            (Atomic::Long, "42"),
+            (Atomic::Real, "4.2e"),
+            (Atomic::Float, "4.2"),
+            (Atomic::Char, "\"a\""),
+            (Atomic::Symbol, "`a"),
+            (Atomic::Date, "2013.02.06"),
+            (Atomic::Month, "2013.02m"),
+            (Atomic::Minute, "12:34"),
+            (Atomic::Second, "12:34:56"),
+            (Atomic::Timespan, "0D12:34:56.123456789"),
+            (Atomic::Timestamp, "2013.02.06D12:34:56.123456789"),
+        ];
+        for (atomic, literal) in samples {
+            assert_eq!(kinds(literal), [TokenKind::Single(atomic)], "{literal}");
+            let value = crate::parse_one(literal).unwrap();
+            assert_eq!(atomic.type_id(), value.type_id(), "{literal}");
+        }
+    }
+
+    #[test]
+    fn diagnostics_name_their_file() {
+        let err = Lexer::new_named("x: \"ab", "script.q")
+            .find_map(Result::err)
+            .unwrap();
+        let mut report = String::new();
+        miette::NarratableReportHandler::new()
+            .render_report(&mut report, err.as_ref())
+            .unwrap();
+        assert!(report.contains("script.q"), "{report}");
+        let unnamed = Lexer::new("x: \"ab").find_map(Result::err).unwrap();
+        let e = unnamed.downcast_ref::<StringTerminationError>().unwrap();
+        assert_eq!(e.src.filename(), None);
+    }
+
+    #[test]
+    fn hex_literals_take_no_suffix() {
+        let err = Lexer::new("0x0aj").next().unwrap().unwrap_err();
+        let e = err.downcast_ref::<InvalidLiteralError>().unwrap();
+        assert_eq!(e.literal, "0x0aj");
+        assert_eq!(kinds("0x0a"), [TokenKind::Single(Atomic::Byte)]);
+    }
+
+    #[test]
+    fn negative_infinities_and_nulls() {
+        assert_eq!(kinds("-0W"), [TokenKind::Single(Atomic::Long)]);
+        assert_eq!(origins("-0W -0N"), ["-0W -0N"]);
+        assert_eq!(origins("x-0W"), ["x", "-", "0W"]);
+        let value = |input| crate::parse_one(input).unwrap();
+        assert_eq!(value("-0W"), Q::Long(-i64::MAX));
+        assert_eq!(value("-0N"), Q::Long(i64::MIN));
+        assert_eq!(value("-0w"), Q::Float(f64::NEG_INFINITY));
+        assert_eq!(value("1 -0W"), Q::Longs(vec![1, -i64::MAX]));
+    }
+
+    #[test]
+    fn builtin_namespaces() {
+        let builtin = |input| {
+            Lexer::new(input)
+                .next()
+                .unwrap()
+                .unwrap()
+                .is_builtin_namespace()
+        };
+        assert!(builtin(".z.p"));
+        assert!(builtin(".Q.fs"));
+        assert!(builtin(".h"));
+        assert!(!builtin(".myns.f"));
+        assert!(!builtin("z"));
+        assert!(!builtin("`.z.p"));
+    }
+
+    #[test]
+    fn string_tokens_decode_their_escapes() {
+        let decoded = |input| Lexer::new(input).next().unwrap().unwrap().as_q_string();
+        assert_eq!(decoded(r#""ab\nc""#), Some(b"ab\nc".to_vec()));
+        assert_eq!(decoded(r#""a\"b\\""#), Some(b"a\"b\\".to_vec()));
+        assert_eq!(decoded(r#""""#), Some(vec![]));
+        assert_eq!(decoded("`ab"), None);
+    }
+
+    #[test]
+    fn adjacent_strings_are_separate_tokens() {
+        let tokens: Vec<_> = Lexer::new(r#""ab""cd" "e""#)
+            .map(|token| {
+                let token = token.unwrap();
+                (token.origin, token.offset, token.kind)
+            })
+            .collect();
+        assert_eq!(
+            tokens,
+            [
+                (r#""ab""#, 0, TokenKind::Vector(Atomic::Char)),
+                (r#""cd""#, 4, TokenKind::Vector(Atomic::Char)),
+                (r#""e""#, 9, TokenKind::Single(Atomic::Char)),
+            ]
+        );
+    }
+
+    #[test]
+    fn integer_literals() {
+        let value = |input| crate::parse_one(input).unwrap();
+        assert_eq!(kinds("5"), [TokenKind::Single(Atomic::Long)]);
+        assert_eq!(value("5"), Q::Long(5));
+        assert_eq!(kinds("5i"), [TokenKind::Single(Atomic::Int)]);
+        assert_eq!(value("5i"), Q::Int(5));
+        assert_eq!(kinds("5h"), [TokenKind::Single(Atomic::Short)]);
+        assert_eq!(value("5h"), Q::Short(5));
+        assert_eq!(origins("123j"), ["123j"]);
+        assert_eq!(value("123j"), Q::Long(123));
+        for out_of_range in ["99999999999999999999", "40000h", "3000000000i"] {
+            let err = Lexer::new(out_of_range).next().unwrap().unwrap_err();
+            let e = err.downcast_ref::<InvalidLiteralError>().unwrap();
+            assert_eq!(
+                e.reason, "it is out of range for its type",
+                "{out_of_range}"
+            );
+            assert_eq!(e.err_span.offset(), 0);
+        }
+    }
+
+    #[test]
+    #[allow(clippy::approx_constant)]
+    fn float_and_real_literals() {
+        let value = |input| crate::parse_one(input).unwrap();
+        for (input, float) in [
+            ("3.14", 3.14),
+            ("1e6", 1e6),
+            (".5", 0.5),
+            ("2.5e-3", 2.5e-3),
+        ] {
+            assert_eq!(kinds(input), [TokenKind::Single(Atomic::Float)], "{input}");
+            assert_eq!(origins(input), [input]);
+            assert_eq!(value(input), Q::Float(float), "{input}");
+        }
+        assert_eq!(kinds("3.14e"), [TokenKind::Single(Atomic::Real)]);
+        assert_eq!(origins("3.14e"), ["3.14e"]);
+        assert_eq!(value("3.14e"), Q::Real(3.14));
+        assert_eq!(kinds("."), [TokenKind::Dot]);
+        let err = Lexer::new("1.2.3").next().unwrap().unwrap_err();
+        let e = err.downcast_ref::<InvalidLiteralError>().unwrap();
+        assert_eq!(e.reason, "a number has at most one decimal point");
+    }
+
+    #[test]
+    fn line_comments_and_the_slash_operator() {
+        assert_eq!(origins("x: 1 / trailing comment\ny"), ["x", ":", "1", "y"]);
+        assert_eq!(origins("/ a whole line\nx"), ["x"]);
+        assert_eq!(
+            kinds("+/x"),
+            [TokenKind::Plus, TokenKind::Slash, TokenKind::Identifier]
+        );
+        assert_eq!(
+            kinds("x/y"),
+            [
+                TokenKind::Identifier,
+                TokenKind::Slash,
+                TokenKind::Identifier
+            ]
+        );
+        // offsets after a comment still point into the source
+        let y = Lexer::new("1 / one\ny").last().unwrap().unwrap();
+        assert_eq!(y.offset, 8);
+    }
+
+    #[test]
+    fn block_comments() {
+        let script = "x\n/\n\"unclosed string\n y: 1 2 3\n / not a line comment\n\\\nz";
+        assert_eq!(origins(script), ["x", "z"]);
+        let z = Lexer::new(script).last().unwrap().unwrap();
+        assert_eq!(z.offset, script.len() - 1);
+        // never closed, it runs to the end of input
+        assert_eq!(origins("x\n/\ny\n\\ not alone\n"), ["x"]);
+    }
+
+    #[test]
+    fn identifiers() {
+        for name in ["abc", "a1", "a_b", ".ns.fn"] {
+            assert_eq!(kinds(name), [TokenKind::Identifier], "{name}");
+            assert_eq!(origins(name), [name]);
+        }
+        // only a leading dot makes a dotted name a single token
+        assert_eq!(
+            kinds("a.b"),
+            [TokenKind::Identifier, TokenKind::Dot, TokenKind::Identifier]
+        );
+        // as in q, `_foo` is the `_` verb applied to `foo`
+        assert_eq!(origins("_foo"), ["_", "foo"]);
+    }
+
+    #[test]
+    fn symbols() {
+        let value = |input| crate::parse_one(input).unwrap();
+        assert_eq!(kinds("`"), [TokenKind::Single(Atomic::Symbol)]);
+        assert_eq!(value("`"), Q::Symbol(Symbol::from("")));
+        assert_eq!(origins("`abc`def"), ["`abc`def"]);
+        assert_eq!(
+            value("`abc`def"),
+            Q::Symbols(vec![Symbol::from("abc"), Symbol::from("def")])
+        );
+        assert_eq!(value("`a.b"), Q::Symbol(Symbol::from("a.b")));
+        assert_eq!(value("`:t.csv"), Q::Symbol(Symbol::from(":t.csv")));
+    }
+
+    #[test]
+    fn adjacent_symbols_make_a_vector() {
+        assert_eq!(kinds("`x"), [TokenKind::Single(Atomic::Symbol)]);
+        assert_eq!(kinds("`x`y"), [TokenKind::Vector(Atomic::Symbol)]);
+        assert_eq!(kinds("``"), [TokenKind::Vector(Atomic::Symbol)]);
+        // a blank between symbols separates them
+        let tokens: Vec<_> = Lexer::new("`a `b`c")
+            .map(|token| {
+                let token = token.unwrap();
+                (token.origin, token.offset, token.kind)
+            })
+            .collect();
+        assert_eq!(
+            tokens,
+            [
+                ("`a", 0, TokenKind::Single(Atomic::Symbol)),
+                ("`b`c", 3, TokenKind::Vector(Atomic::Symbol)),
+            ]
+        );
+    }
+
+    #[test]
+    fn comparison_and_colon_operators() {
+        let operators = [
+            ("<", TokenKind::Less),
+            (">", TokenKind::Greater),
+            ("=", TokenKind::Equal),
+            ("<=", TokenKind::LessEqual),
+            (">=", TokenKind::GreaterEqual),
+            ("<>", TokenKind::NotEqual),
+            (":", TokenKind::Colon),
+            ("::", TokenKind::ColonColon),
+            ("'", TokenKind::Quote),
+            ("':", TokenKind::QuoteColon),
+        ];
+        for (op, kind) in operators {
+            let input = format!("a{op}b");
+            assert_eq!(
+                kinds(&input),
+                [TokenKind::Identifier, kind, TokenKind::Identifier],
+                "{input}"
+            );
+        }
+        // the longest match wins, and a blank splits a pair
+        assert_eq!(origins("a<>b"), ["a", "<>", "b"]);
+        assert_eq!(origins("a<=b"), ["a", "<=", "b"]);
+        assert_eq!(origins("x::y"), ["x", "::", "y"]);
+        assert_eq!(origins("x: :y"), ["x", ":", ":", "y"]);
+        assert_eq!(origins("a< >b"), ["a", "<", ">", "b"]);
+    }
+
+    #[test]
+    fn byte_literals() {
+        let value = |input| crate::parse_one(input).unwrap();
+        assert_eq!(kinds("0x00"), [TokenKind::Single(Atomic::Byte)]);
+        assert_eq!(value("0x00"), Q::Byte(0));
+        assert_eq!(kinds("0xdeadbeef"), [TokenKind::Vector(Atomic::Byte)]);
+        assert_eq!(value("0xdeadbeef"), Q::Bytes(vec![0xde, 0xad, 0xbe, 0xef]));
+        let err = Lexer::new("x 0xabc").nth(1).unwrap().unwrap_err();
+        let e = err.downcast_ref::<InvalidLiteralError>().unwrap();
+        assert_eq!(e.literal, "0xabc");
+        assert_eq!((e.err_span.offset(), e.err_span.len()), (2, 5));
+    }
+
+    #[test]
+    fn boolean_literals() {
+        let value = |input| crate::parse_one(input).unwrap();
+        assert_eq!(kinds("1b"), [TokenKind::Single(Atomic::Boolean)]);
+        assert_eq!(value("1b"), Q::Boolean(true));
+        assert_eq!(value("0b"), Q::Boolean(false));
+        assert_eq!(kinds("010101b"), [TokenKind::Vector(Atomic::Boolean)]);
+        assert_eq!(
+            value("010101b"),
+            Q::Booleans(vec![false, true, false, true, false, true])
+        );
+        let err = Lexer::new("12b").next().unwrap().unwrap_err();
+        let e = err.downcast_ref::<InvalidLiteralError>().unwrap();
+        assert_eq!(e.literal, "12b");
+    }
+
+    #[test]
+    fn colons_in_times_and_operators() {
+        use crate::qtype::chrono::{Minute, Month, Second};
+        assert_eq!(kinds("12:30"), [TokenKind::Single(Atomic::Minute)]);
+        assert_eq!(kinds("12:30:45"), [TokenKind::Single(Atomic::Second)]);
+        assert_eq!(kinds("2000.01m"), [TokenKind::Single(Atomic::Month)]);
+        assert_eq!(
+            kinds("x::y"),
+            [
+                TokenKind::Identifier,
+                TokenKind::ColonColon,
+                TokenKind::Identifier
+            ]
+        );
+        assert_eq!(
+            kinds("x:12:30"),
+            [
+                TokenKind::Identifier,
+                TokenKind::Colon,
+                TokenKind::Single(Atomic::Minute)
+            ]
+        );
+        assert_eq!(kinds(":"), [TokenKind::Colon]);
+        assert_eq!(origins("x:12:30"), ["x", ":", "12:30"]);
+        assert_eq!(
+            crate::parse_one("12:30").unwrap(),
+            Q::Minute(Minute::from_i32(750))
+        );
+        assert_eq!(
+            crate::parse_one("12:30:45").unwrap(),
+            Q::Second(Second::from_i32(45045))
+        );
+        assert_eq!(
+            crate::parse_one("2000.01m").unwrap(),
+            Q::Month(Month::from_i32(0))
+        );
+    }
+}
@@ -3,5 +3,6 @@ pub mod parse;
 pub mod qtype;
 
 pub use lex::{Lexer, Token, TokenKind};
-pub use parse::Parser;
+pub use parse::{Expr, Parser, SystemDirective, parse_one, parse_vector};
 pub use qtype::chrono;
+pub use qtype::{Q, QError};
@@ -1,23 +1,1375 @@
-use crate::lex::Lexer;
-use crate::qtype::Q;
+use crate::lex::{
+    self, Atomic, InvalidLiteralError, Lexer, Source, Token, TokenKind, line_end, unescape,
+};
+use crate::qtype::chrono::{Date, Minute, Month, Second, Timespan, Timestamp};
+use crate::qtype::display::{TYPE_CHARS, type_name};
+use crate::qtype::symbol::Symbol;
+use crate::qtype::{Q, QError};
+use miette::{Diagnostic, Error, SourceSpan};
+use thiserror::Error;
 
-pub struct Parser<'de> {
-    source: &'de str,
-    lexer: Lexer<'de>,
+// Keywords which are applied infix when they have a left operand, e.g. `7 mod 3`
+const INFIX_KEYWORDS: &[&str] = &[
+    "aj", "and", "asof", "bin", "binr", "cor", "cov", "cross", "cut", "div", "each", "ema",
+    "except", "fby", "ij", "in", "inter", "like", "lj", "mavg", "mcount", "mdev", "mmax", "mmin",
+    "mmu", "mod", "msum", "or", "over", "peach", "prior", "scan", "scov", "set", "ss", "sublist",
+    "sv", "uj", "union", "upsert", "vs", "wavg", "within", "wsum", "xasc", "xbar", "xcol", "xcols",
+    "xdesc", "xexp", "xkey", "xlog", "xprev", "xrank",
+];
+
+#[derive(Diagnostic, Debug, Error)]
+#[error("{message}")]
+pub struct ParseError {
+    #[source_code]
+    src: Source,
+
+    pub message: String,
+
+    #[label = "here"]
+    err_span: SourceSpan,
+
+    #[help]
+    help: Option<&'static str>,
+}
+
+impl ParseError {
+    pub fn line(&self) -> usize {
+        let until_unrecongized = &self.src[..self.err_span.offset().min(self.src.len())];
+        until_unrecongized.lines().count().max(1)
+    }
 }
 
-// impl<'de> Parser<'de> {
-//     pub fn new(input: &'de str) -> Result<Self, Error> {
-//         Ok(Self {
-//             tokens: preprocess(input)?,
-//         })
-//     }
-// }
+/// Names the file behind the source of a parser diagnostic, or of a lexer diagnostic passed on
+/// by the parser
+fn with_filename(mut err: Error, filename: Option<&str>) -> Error {
+    if let (Some(filename), Some(e)) = (filename, err.downcast_mut::<ParseError>()) {
+        e.src.set_filename(filename);
+        return err;
+    }
+    lex::with_filename(err, filename)
+}
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Expr {
-    Identifier,
+    Identifier(Symbol),
     Atom(Q),
-    Vector(Vec<Q>),  // homogeneous list
+    Vector(Q),       // homogeneous list
     List(Vec<Expr>), // heterogeneous/nested list
+    Verb(TokenKind), // primitive operator, e.g. `+`
+    Adverb {
+        adverb: TokenKind,
+        operand: Box<Expr>,
+    }, // derived function, e.g. `+/`
+    Apply {
+        func: Box<Expr>,
+        args: Vec<Expr>,
+    }, // f[x;y], f x, x+y
+    Projection {
+        func: Box<Expr>,
+        args: Vec<Option<Expr>>,
+    }, // f[;y], +[2], 2+
+    Assign {
+        name: Symbol,
+        value: Box<Expr>,
+    }, // x:1
+    Lambda {
+        params: Vec<Symbol>,
+        body: Vec<Expr>,
+    }, // {[x;y] x+y}
+    Table {
+        keys: Vec<(Symbol, Expr)>,
+        columns: Vec<(Symbol, Expr)>,
+    }, // ([k:1 2] c:`a`b)
+    SystemDirective(SystemDirective), // \\ or \ alone on a line
+}
+
+/// The implicit params of a lambda without a parameter list: `x`, `y`, `z` up to the highest one
+/// used in `body`, so `{x+z}` takes three arguments and `{1}` still takes `x`
+fn implicit_params(body: &[Expr]) -> Vec<Symbol> {
+    let rank = body.iter().map(implicit_rank).max().unwrap_or(0).max(1);
+    ["x", "y", "z"][..rank]
+        .iter()
+        .map(|&name| Symbol::from(name))
+        .collect()
+}
+
+fn implicit_rank(expr: &Expr) -> usize {
+    let rank = |name: &Symbol| match name.resolve() {
+        "x" => 1,
+        "y" => 2,
+        "z" => 3,
+        _ => 0,
+    };
+    let highest =
+        |exprs: &mut dyn Iterator<Item = &Expr>| exprs.map(implicit_rank).max().unwrap_or(0);
+    match expr {
+        Expr::Identifier(name) => rank(name),
+        Expr::List(items) => highest(&mut items.iter()),
+        Expr::Adverb { operand, .. } => implicit_rank(operand),
+        Expr::Apply { func, args } => implicit_rank(func).max(highest(&mut args.iter())),
+        Expr::Projection { func, args } => {
+            implicit_rank(func).max(highest(&mut args.iter().flatten()))
+        }
+        Expr::Assign { name, value } => rank(name).max(implicit_rank(value)),
+        Expr::Table { keys, columns } => {
+            highest(&mut keys.iter().chain(columns).map(|(_, column)| column))
+        }
+        // a nested lambda's x, y and z are its own
+        Expr::Lambda { .. }
+        | Expr::Atom(_)
+        | Expr::Vector(_)
+        | Expr::Verb(_)
+        | Expr::SystemDirective(_) => 0,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SystemDirective {
+    Exit,    // \\
+    ToggleK, // \
+}
+
+impl Expr {
+    /// Whether the expression takes a left operand when one is available
+    fn is_verb(&self) -> bool {
+        match self {
+            Expr::Verb(_) | Expr::Adverb { .. } => true,
+            Expr::Identifier(name) => INFIX_KEYWORDS.contains(&name.resolve()),
+            _ => false,
+        }
+    }
+}
+
+pub struct Parser<'de> {
+    source: &'de str,
+    lexer: Lexer<'de>,
+    end: usize, // byte offset right after the last consumed token
+    depth: usize,
+    filename: Option<String>,
+}
+
+impl<'de> Parser<'de> {
+    pub fn new(input: &'de str) -> Self {
+        Self {
+            source: input,
+            lexer: Lexer::new(input),
+            end: 0,
+            depth: 0,
+            filename: None,
+        }
+    }
+
+    /// A parser over the contents of the file `filename`, which its diagnostics name
+    pub fn new_named(input: &'de str, filename: &str) -> Self {
+        Self {
+            lexer: Lexer::new_named(input, filename),
+            filename: Some(filename.to_string()),
+            ..Self::new(input)
+        }
+    }
+
+    /// Parse the whole input into a list of statements
+    pub fn parse(&mut self) -> Result<Vec<Expr>, Error> {
+        self.parse_statements()
+            .map_err(|e| with_filename(e, self.filename.as_deref()))
+    }
+
+    fn parse_statements(&mut self) -> Result<Vec<Expr>, Error> {
+        let mut statements = Vec::new();
+        loop {
+            if let Some(directive) = self.parse_directive()? {
+                statements.push(Expr::SystemDirective(directive));
+                if directive == SystemDirective::Exit {
+                    // nothing after an exit is ever run
+                    break;
+                }
+                continue;
+            }
+            if let Some(expr) = self.parse_expr()? {
+                statements.push(expr);
+            }
+            match self.peek_token()? {
+                None => break,
+                Some(token) if token.kind == TokenKind::Semicolon => {
+                    self.next_token()?;
+                }
+                Some(token) if self.starts_line(&token) => {}
+                Some(token) => {
+                    self.next_token()?;
+                    return Err(self.unexpected(token));
+                }
+            }
+        }
+        Ok(statements)
+    }
+
+    /// Parse `\\` (exit) or `\` (toggle k mode) standing alone on a line
+    fn parse_directive(&mut self) -> Result<Option<SystemDirective>, Error> {
+        let Some(token) = self.peek_token()? else {
+            return Ok(None);
+        };
+        let directive = match token.kind {
+            TokenKind::BackslashBackslash => SystemDirective::Exit,
+            TokenKind::BackSlash => SystemDirective::ToggleK,
+            _ => return Ok(None),
+        };
+        let after = &self.source[token.offset + token.origin.len()..];
+        let alone = (token.offset == 0 || self.starts_line(&token))
+            && after[..line_end(after)].trim().is_empty();
+        if !alone {
+            return Ok(None);
+        }
+        self.next_token()?;
+        Ok(Some(directive))
+    }
+
+    /// Parse one expression, stopping before `;`, a closing bracket or a new line. Returns
+    /// `None` for an empty expression.
+    fn parse_expr(&mut self) -> Result<Option<Expr>, Error> {
+        let mut items = Vec::new();
+        while let Some(token) = self.peek_token()? {
+            let terminates =
+                matches!(
+                    token.kind,
+                    TokenKind::Semicolon
+                        | TokenKind::RightParen
+                        | TokenKind::RightBracket
+                        | TokenKind::RightBrace
+                ) || (!items.is_empty() && self.depth == 0 && self.starts_line(&token));
+            if terminates {
+                break;
+            }
+            items.push(self.parse_item()?);
+        }
+        Ok(fold(items))
+    }
+
+    /// Parse a noun or a verb, together with any brackets and adverbs applied to it
+    fn parse_item(&mut self) -> Result<Expr, Error> {
+        let token = self
+            .next_token()?
+            .expect("parse_item is called after a peek");
+        let mut item = match token.kind {
+            TokenKind::Single(_) => Expr::Atom(self.literal(token)?),
+            TokenKind::Vector(_) => Expr::Vector(self.literal(token)?),
+            TokenKind::Identifier => Expr::Identifier(Symbol::from(token.origin)),
+            TokenKind::LeftParen => self.parse_list()?,
+            TokenKind::LeftBrace => self.parse_lambda()?,
+            TokenKind::LeftBracket
+            | TokenKind::Slash
+            | TokenKind::BackSlash
+            | TokenKind::BackslashBackslash
+            | TokenKind::Quote
+            | TokenKind::QuoteColon
+            | TokenKind::SlashColon
+            | TokenKind::BackslashColon
+            | TokenKind::Eof => return Err(self.unexpected(token)),
+            kind => Expr::Verb(kind),
+        };
+
+        loop {
+            let Some(next) = self.peek_token()? else {
+                break;
+            };
+            match next.kind {
+                TokenKind::LeftBracket => {
+                    self.next_token()?;
+                    item = self.parse_apply(item)?;
+                }
+                TokenKind::Slash
+                | TokenKind::BackSlash
+                | TokenKind::Quote
+                | TokenKind::QuoteColon
+                | TokenKind::SlashColon
+                | TokenKind::BackslashColon
+                    if next.offset == self.end =>
+                {
+                    self.next_token()?;
+                    item = Expr::Adverb {
+                        adverb: next.kind,
+                        operand: Box::new(item),
+                    };
+                }
+                _ => break,
+            }
+        }
+        Ok(item)
+    }
+
+    /// Parse `(a;b;c)` after the opening paren. `()` is the empty list and `(::)` the generic
+    /// null, while `(a)` is just `a`. Elided items, as in `(1;;2)`, are generic nulls.
+    fn parse_list(&mut self) -> Result<Expr, Error> {
+        if self
+            .peek_token()?
+            .is_some_and(|token| token.kind == TokenKind::LeftBracket)
+        {
+            return self.parse_table();
+        }
+        let items = self.parse_delimited(TokenKind::RightParen)?;
+        match items.len() {
+            1 => Ok(match items.into_iter().next().unwrap() {
+                None => Expr::Vector(Q::List(vec![])),
+                Some(Expr::Verb(TokenKind::ColonColon)) => Expr::Atom(Q::Null),
+                Some(item) => item,
+            }),
+            _ => Ok(Expr::List(
+                items
+                    .into_iter()
+                    .map(|item| item.unwrap_or(Expr::Atom(Q::Null)))
+                    .collect(),
+            )),
+        }
+    }
+
+    /// Parse `([k:keys] c:values; ...)` after the opening paren. A bare name `c` is short for
+    /// `c:c`, and `[]` has no key columns. When every column is a literal, this lowers to the
+    /// table, keyed if there are key columns.
+    fn parse_table(&mut self) -> Result<Expr, Error> {
+        let start = self.end - 1;
+        self.next_token()?;
+        let keys = self.parse_delimited(TokenKind::RightBracket)?;
+        let keys = self.columns(keys, start)?;
+        let columns = self.parse_delimited(TokenKind::RightParen)?;
+        let columns = self.columns(columns, start)?;
+        match table_literal(&keys, &columns) {
+            None => Ok(Expr::Table { keys, columns }),
+            Some(Ok(table)) => Ok(Expr::Vector(table)),
+            Some(Err(_)) => Err(ParseError {
+                src: Source::new(self.source),
+                message: "Columns of different counts".to_string(),
+                err_span: SourceSpan::from(start..self.end),
+                help: Some("every column of a table must have the same count"),
+            }
+            .into()),
+        }
+    }
+
+    /// Parse `{[x;y] body}` after the opening brace. Without a parameter list, the params are
+    /// inferred from the body as q does, see `implicit_params`. The body is `;`-separated
+    /// statements where an empty one is the generic null, so `{}` has no statements but `{x;}`
+    /// has two.
+    fn parse_lambda(&mut self) -> Result<Expr, Error> {
+        let mut params = Vec::new();
+        let explicit = self
+            .peek_token()?
+            .is_some_and(|token| token.kind == TokenKind::LeftBracket);
+        if explicit {
+            self.next_token()?;
+            let mut separated = true;
+            loop {
+                let Some(token) = self.next_token()? else {
+                    return Err(self.unbalanced());
+                };
+                match token.kind {
+                    TokenKind::Identifier if separated => {
+                        params.push(Symbol::from(token.origin));
+                        separated = false;
+                    }
+                    TokenKind::Semicolon if !separated => separated = true,
+                    TokenKind::RightBracket if !separated || params.is_empty() => break,
+                    _ => return Err(self.unexpected(token)),
+                }
+            }
+        }
+        let statements = self.parse_delimited(TokenKind::RightBrace)?;
+        let body = match statements.as_slice() {
+            [None] => vec![],
+            _ => statements
+                .into_iter()
+                .map(|statement| statement.unwrap_or(Expr::Atom(Q::Null)))
+                .collect(),
+        };
+        if !explicit {
+            params = implicit_params(&body);
+        }
+        Ok(Expr::Lambda { params, body })
+    }
+
+    /// Parse `func[a;b]` after the opening bracket. Elided arguments, or a dyadic primitive
+    /// given a single argument, make a projection.
+    fn parse_apply(&mut self, func: Expr) -> Result<Expr, Error> {
+        let mut args = self.parse_delimited(TokenKind::RightBracket)?;
+        if args.len() == 1 && args[0].is_none() {
+            // f[] applies f to nothing
+            args.clear();
+        }
+        if matches!(func, Expr::Verb(_)) && args.len() == 1 {
+            args.push(None);
+        }
+        if args.iter().any(Option::is_none) {
+            Ok(Expr::Projection {
+                func: Box::new(func),
+                args,
+            })
+        } else {
+            Ok(Expr::Apply {
+                func: Box::new(func),
+                args: args.into_iter().flatten().collect(),
+            })
+        }
+    }
+
+    /// Parse `;`-separated expressions up to and including `close`
+    fn parse_delimited(&mut self, close: TokenKind) -> Result<Vec<Option<Expr>>, Error> {
+        self.depth += 1;
+        let mut items = Vec::new();
+        loop {
+            items.push(self.parse_expr()?);
+            match self.next_token()? {
+                Some(token) if token.kind == TokenKind::Semicolon => continue,
+                Some(token) if token.kind == close => break,
+                Some(token) => return Err(self.unexpected(token)),
+                None => return Err(self.unbalanced()),
+            }
+        }
+        self.depth -= 1;
+        Ok(items)
+    }
+
+    /// Convert a literal token into its q value
+    fn literal(&self, token: Token<'de>) -> Result<Q, Error> {
+        let (atomic, is_vector) = match token.kind {
+            TokenKind::Single(atomic) => (atomic, false),
+            TokenKind::Vector(atomic) => (atomic, true),
+            _ => unreachable!(),
+        };
+        let origin = token.origin;
+        let invalid = |reason: &'static str| -> Error {
+            InvalidLiteralError::new(
+                self.source,
+                origin,
+                reason,
+                token.offset..token.offset + origin.len(),
+                None,
+            )
+            .into()
+        };
+
+        match atomic {
+            Atomic::Char => {
+                let bytes = unescape(&origin[1..origin.len() - 1]);
+                if bytes.len() == 1 {
+                    Ok(Q::Char(bytes[0]))
+                } else {
+                    Ok(Q::String(bytes))
+                }
+            }
+            Atomic::Symbol => {
+                let mut symbols = origin.split('`').skip(1).map(Symbol::from);
+                if is_vector {
+                    Ok(Q::Symbols(symbols.collect()))
+                } else {
+                    Ok(Q::Symbol(symbols.next().unwrap()))
+                }
+            }
+            Atomic::Byte => {
+                let hex = &origin[2..];
+                let padded = if hex.len() % 2 == 1 {
+                    format!("0{hex}")
+                } else {
+                    hex.to_string()
+                };
+                let bytes = (0..padded.len())
+                    .step_by(2)
+                    .map(|i| u8::from_str_radix(&padded[i..i + 2], 16))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| invalid("invalid hex digits"))?;
+                match bytes.as_slice() {
+                    [byte] if !is_vector => Ok(Q::Byte(*byte)),
+                    _ => Ok(Q::Bytes(bytes)),
+                }
+            }
+            Atomic::Boolean => {
+                let bits: Vec<bool> = origin[..origin.len() - 1]
+                    .bytes()
+                    .map(|b| b == b'1')
+                    .collect();
+                match bits.as_slice() {
+                    [bit] if !is_vector => Ok(Q::Boolean(*bit)),
+                    _ => Ok(Q::Booleans(bits)),
+                }
+            }
+            _ => {
+                let body = match origin.chars().last() {
+                    Some(c) if Atomic::from_suffix(c) == Some(atomic) => {
+                        &origin[..origin.len() - 1]
+                    }
+                    _ => origin,
+                };
+                let elems: Vec<&str> = body.split_whitespace().collect();
+                // `0n` and `0w` are floats even without a suffix
+                let atomic = if atomic == Atomic::Long
+                    && elems
+                        .iter()
+                        .any(|e| matches!(e.trim_start_matches('-'), "0n" | "0w"))
+                {
+                    Atomic::Float
+                } else {
+                    atomic
+                };
+                numeric_literal(atomic, &elems, is_vector).map_err(invalid)
+            }
+        }
+    }
+
+    // Helper methods
+    fn next_token(&mut self) -> Result<Option<Token<'de>>, Error> {
+        match self.lexer.next() {
+            None => Ok(None),
+            Some(Ok(token)) => {
+                self.end = token.offset + token.origin.len();
+                Ok(Some(token))
+            }
+            Some(Err(e)) => Err(e),
+        }
+    }
+
+    fn peek_token(&mut self) -> Result<Option<Token<'de>>, Error> {
+        match self.lexer.peek() {
+            None => Ok(None),
+            Some(Ok(token)) => Ok(Some(*token)),
+            Some(Err(_)) => Err(self.lexer.next().unwrap().unwrap_err()),
+        }
+    }
+
+    /// A token at the start of a line begins a new statement in a q script. `\r\n` and `\r`
+    /// line endings count as line breaks too.
+    fn starts_line(&self, token: &Token) -> bool {
+        token.offset > 0 && matches!(self.source.as_bytes()[token.offset - 1], b'\n' | b'\r')
+    }
+
+    fn unbalanced(&self) -> Error {
+        ParseError {
+            src: Source::new(self.source),
+            message: "Unbalanced brackets".to_string(),
+            err_span: SourceSpan::from(self.source.len()..self.source.len()),
+            help: Some("expected a closing bracket before the end of input"),
+        }
+        .into()
+    }
+
+    /// The `name:values` items of a table literal starting at `start`
+    fn columns(
+        &self,
+        items: Vec<Option<Expr>>,
+        start: usize,
+    ) -> Result<Vec<(Symbol, Expr)>, Error> {
+        if let [None] = items.as_slice() {
+            return Ok(vec![]);
+        }
+        items
+            .into_iter()
+            .map(|item| match item {
+                Some(Expr::Assign { name, value }) => Ok((name, *value)),
+                Some(Expr::Identifier(name)) => Ok((name, Expr::Identifier(name))),
+                _ => Err(ParseError {
+                    src: Source::new(self.source),
+                    message: "Expected a column definition".to_string(),
+                    err_span: SourceSpan::from(start..self.end),
+                    help: Some("columns of a table literal are written `name:values`"),
+                }
+                .into()),
+            })
+            .collect()
+    }
+
+    fn unexpected(&self, token: Token) -> Error {
+        ParseError {
+            src: Source::new(self.source),
+            message: format!("Unexpected token '{}'", token.origin),
+            err_span: SourceSpan::from(token.offset..token.offset + token.origin.len()),
+            help: None,
+        }
+        .into()
+    }
+}
+
+/// Parse `input` as a simple vector whose items are separated by `sep` instead of spaces,
+/// e.g. `1,2,3` with `,`. Bare words are symbols, longs among floats become floats and empty
+/// items are nulls of the vector's type.
+pub fn parse_vector(input: &str, sep: char) -> Result<Q, Error> {
+    let mut items = Vec::new();
+    let mut offset = 0;
+    for field in input.split(sep) {
+        let start = offset + field.len() - field.trim_start().len();
+        offset += field.len() + sep.len_utf8();
+        let field = field.trim();
+        if field.is_empty() {
+            items.push(None);
+            continue;
+        }
+        let item = match Parser::new(field).parse()?.as_slice() {
+            [Expr::Atom(atom)] if atom.is_atom() => atom.clone(),
+            [Expr::Identifier(name)] => Q::Symbol(*name),
+            _ => {
+                return Err(ParseError {
+                    src: Source::new(input),
+                    message: format!("Expected an atom, found '{field}'"),
+                    err_span: SourceSpan::from(start..start + field.len()),
+                    help: Some("each item must be a single atom"),
+                }
+                .into());
+            }
+        };
+        items.push(Some(item));
+    }
+
+    let present: Vec<&Q> = items.iter().flatten().collect();
+    if present.iter().any(|q| matches!(q, Q::Float(_)))
+        && present
+            .iter()
+            .all(|q| matches!(q, Q::Long(_) | Q::Float(_)))
+    {
+        for item in items.iter_mut().flatten() {
+            if let Q::Long(x) = item {
+                *item = Q::Float(*x as f64);
+            }
+        }
+    }
+    let null = items.iter().flatten().next().map_or(Q::Null, Q::null_item);
+    Ok(Q::collapse(
+        items
+            .into_iter()
+            .map(|item| item.unwrap_or_else(|| null.clone()))
+            .collect(),
+    ))
+}
+
+/// Parse `input` as a single q value written out literally: an atom, a vector, a list of
+/// values, a dictionary `keys!values` or a table `flip keys!columns`, where `enlist` makes
+/// one-item lists
+pub fn parse_one(input: &str) -> Result<Q, Error> {
+    let statements = Parser::new(input).parse()?;
+    let value = match statements.as_slice() {
+        [expr] => literal(expr),
+        _ => None,
+    };
+    value.ok_or_else(|| {
+        // empty statements are skipped, so `;` alone holds no value at all
+        let message = if statements.is_empty() {
+            "Expected a literal value, found none"
+        } else {
+            "Expected a single literal value"
+        };
+        ParseError {
+            src: Source::new(input),
+            message: message.to_string(),
+            err_span: SourceSpan::from(0..input.len()),
+            help: Some("only constants, lists of them, `!`, `flip` and `enlist` are evaluated"),
+        }
+        .into()
+    })
+}
+
+/// The value of an expression built only from literals
+fn literal(expr: &Expr) -> Option<Q> {
+    match expr {
+        Expr::Atom(q) | Expr::Vector(q) => Some(q.clone()),
+        // only atoms merge into a vector: lists of vectors stay general lists whether ragged
+        // or rectangular, e.g. `(1 2;3 4 5)` and `(1 2;3 4)`
+        Expr::List(items) => items
+            .iter()
+            .map(literal)
+            .collect::<Option<_>>()
+            .map(Q::collapse),
+        Expr::Apply { func, args } => match (func.as_ref(), args.as_slice()) {
+            (Expr::Verb(TokenKind::Bang), [keys, values]) => {
+                let (keys, values) = (literal(keys)?, literal(values)?);
+                let keyed = matches!((&keys, &values), (Q::Table(..), Q::Table(..)));
+                (keyed || (!keys.is_atom() && keys.len() == values.len()))
+                    .then(|| Q::Dict(Box::new(keys), Box::new(values)))
+            }
+            (Expr::Identifier(name), [dict]) if name.resolve() == "flip" => {
+                literal(dict)?.flip().ok()
+            }
+            (Expr::Verb(TokenKind::Comma), [item]) => Some(literal(item)?.enlist()),
+            (Expr::Identifier(name), [item]) if name.resolve() == "enlist" => {
+                Some(literal(item)?.enlist())
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The table of literal columns, with atoms repeated to the count of the others, or `None`
+/// if a column is not a literal
+fn table_literal(keys: &[(Symbol, Expr)], columns: &[(Symbol, Expr)]) -> Option<Result<Q, QError>> {
+    let values: Vec<Q> = keys
+        .iter()
+        .chain(columns)
+        .map(|(_, column)| literal(column))
+        .collect::<Option<_>>()?;
+    let count = values.iter().find(|v| !v.is_atom()).map_or(1, Q::len);
+    let table = |names: &[(Symbol, Expr)], values: &[Q]| -> Result<Q, QError> {
+        let values = values
+            .iter()
+            .map(|v| {
+                if v.is_atom() {
+                    Q::fill_vector(v, count)
+                } else {
+                    Ok(v.clone())
+                }
+            })
+            .collect::<Result<_, _>>()?;
+        Q::table(names.iter().map(|(name, _)| *name).collect(), values)
+    };
+    let (key_values, values) = values.split_at(keys.len());
+    Some(if keys.is_empty() {
+        table(columns, values)
+    } else {
+        table(keys, key_values).and_then(|keys| {
+            let values = table(columns, values)?;
+            if keys.len() != values.len() {
+                return Err(QError::Length);
+            }
+            Ok(Q::Dict(Box::new(keys), Box::new(values)))
+        })
+    })
+}
+
+/// Combine parsed items right to left: a verb takes the noun on its left as its left operand,
+/// any other item is applied to everything on its right.
+fn fold(mut items: Vec<Expr>) -> Option<Expr> {
+    let mut rhs = items.pop()?;
+    if rhs.is_verb() && items.last().is_some_and(|item| !item.is_verb()) {
+        // a dyadic verb without its right operand, e.g. `2+`
+        let lhs = items.pop().unwrap();
+        rhs = Expr::Projection {
+            func: Box::new(rhs),
+            args: vec![Some(lhs), None],
+        };
+    }
+    while let Some(item) = items.pop() {
+        rhs = if item.is_verb() && items.last().is_some_and(|item| !item.is_verb()) {
+            let lhs = items.pop().unwrap();
+            match (&item, lhs) {
+                (Expr::Verb(TokenKind::Colon), Expr::Identifier(name)) => Expr::Assign {
+                    name,
+                    value: Box::new(rhs),
+                },
+                (_, lhs) => match empty_vector(&item, &lhs, &rhs) {
+                    Some(empty) => Expr::Vector(empty),
+                    None => Expr::Apply {
+                        func: Box::new(item),
+                        args: vec![lhs, rhs],
+                    },
+                },
+            }
+        } else {
+            Expr::Apply {
+                func: Box::new(item),
+                args: vec![rhs],
+            }
+        };
+    }
+    Some(rhs)
+}
+
+/// The empty typed vector written as `0#x` or as a cast of `()`, e.g. `` `long$() `` or
+/// `` "j"$() ``
+fn empty_vector(verb: &Expr, lhs: &Expr, rhs: &Expr) -> Option<Q> {
+    match (verb, lhs, rhs) {
+        (Expr::Verb(TokenKind::Hash), Expr::Atom(Q::Long(0)), Expr::Atom(x) | Expr::Vector(x)) => {
+            Q::empty(x.type_id().abs()).ok()
+        }
+        (Expr::Verb(TokenKind::Dollar), Expr::Atom(target), Expr::Vector(Q::List(v)))
+            if v.is_empty() =>
+        {
+            let type_id = match target {
+                Q::Symbol(name) => (1..=18).find(|&t| {
+                    Q::empty(t).is_ok_and(|empty| type_name(&empty) == name.resolve())
+                })?,
+                Q::Char(c) => TYPE_CHARS.find(*c as char)? as i8,
+                _ => return None,
+            };
+            Q::empty(type_id).ok()
+        }
+        _ => None,
+    }
+}
+
+/// Build an atom or a vector of the given type from the whitespace-separated elements of a
+/// literal
+fn numeric_literal(atomic: Atomic, elems: &[&str], is_vector: bool) -> Result<Q, &'static str> {
+    fn each<T>(
+        elems: &[&str],
+        f: impl Fn(&str) -> Result<T, String>,
+    ) -> Result<Vec<T>, &'static str> {
+        elems
+            .iter()
+            .map(|e| f(e))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| "it is not a valid literal of its type")
+    }
+    // null has no sign, so `-0N` is just `0N`
+    let elems: Vec<&str> = elems
+        .iter()
+        .map(|&e| match e {
+            "-0N" => "0N",
+            "-0n" => "0n",
+            e => e,
+        })
+        .collect();
+    let elems = elems.as_slice();
+    macro_rules! integer {
+        ($t:ty) => {
+            |e: &str| match e {
+                "0N" => Ok(<$t>::MIN),
+                "0W" => Ok(<$t>::MAX),
+                "-0W" => Ok(-<$t>::MAX),
+                _ => e.parse::<$t>().map_err(|e| e.to_string()),
+            }
+        };
+    }
+    macro_rules! float {
+        ($t:ty) => {
+            |e: &str| match e {
+                "0N" | "0n" => Ok(<$t>::NAN),
+                "0W" | "0w" => Ok(<$t>::INFINITY),
+                "-0W" | "-0w" => Ok(<$t>::NEG_INFINITY),
+                _ => e.parse::<$t>().map_err(|e| e.to_string()),
+            }
+        };
+    }
+
+    macro_rules! build {
+        ($atom:ident, $vector:ident, $values:expr) => {{
+            let values = $values;
+            match values.as_slice() {
+                [value] if !is_vector => Q::$atom(*value),
+                _ => Q::$vector(values),
+            }
+        }};
+    }
+
+    let q = match atomic {
+        Atomic::Short => build!(Short, Shorts, each(elems, integer!(i16))?),
+        Atomic::Int => build!(Int, Ints, each(elems, integer!(i32))?),
+        Atomic::Long => build!(Long, Longs, each(elems, integer!(i64))?),
+        Atomic::Real => build!(Real, Reals, each(elems, float!(f32))?),
+        Atomic::Float => build!(Float, Floats, each(elems, float!(f64))?),
+        Atomic::Date => build!(
+            Date,
+            Dates,
+            each(elems, |e| Date::from_literal(&typed(e, 'd')))?
+        ),
+        Atomic::Month => build!(
+            Month,
+            Months,
+            each(elems, |e| Month::from_literal(&format!("{e}m")))?
+        ),
+        Atomic::Minute => build!(
+            Minute,
+            Minutes,
+            each(elems, |e| Minute::from_literal(&typed(
+                &padded_hour(e),
+                'u'
+            )))?
+        ),
+        Atomic::Second => build!(
+            Second,
+            Seconds,
+            each(elems, |e| Second::from_literal(&typed(
+                &padded_hour(e),
+                'v'
+            )))?
+        ),
+        Atomic::Timespan => build!(
+            Timespan,
+            Timespans,
+            each(elems, |e| {
+                if e.contains('D') || is_special(e) {
+                    Timespan::from_literal(&typed(e, 'n'))
+                } else {
+                    Timespan::from_literal(&format!("0D{e}"))
+                }
+            })?
+        ),
+        Atomic::Timestamp => build!(
+            Timestamp,
+            Timestamps,
+            each(elems, |e| {
+                if is_special(e) {
+                    return Timestamp::from_literal(&typed(e, 'p'));
+                }
+                let (date, time) = e.split_once('D').ok_or_else(|| format!("'{e}"))?;
+                let (time, nanos) = time.split_once('.').unwrap_or((time, ""));
+                // missing fields of the time are zero, e.g. `2000.01.01D` and `2000.01.01D12:30`
+                let time = match time.split(':').count() {
+                    _ if time.is_empty() => "00:00:00".to_string(),
+                    1 if nanos.is_empty() => format!("{time}:00:00"),
+                    2 if nanos.is_empty() => format!("{time}:00"),
+                    _ => time.to_string(),
+                };
+                Timestamp::from_literal(&format!("{date}D{time}.{nanos:0<9}"))
+            })?
+        ),
+        Atomic::Boolean | Atomic::Byte | Atomic::Char | Atomic::Symbol => unreachable!(),
+    };
+    Ok(q)
+}
+
+/// Whether `elem` is a null or an infinity, i.e. `0N`, `0W` or `-0W`
+fn is_special(elem: &str) -> bool {
+    matches!(elem, "0N" | "0W" | "-0W")
+}
+
+/// A time with its one-digit hour padded, as q reads `1:30` as `01:30`
+fn padded_hour(elem: &str) -> String {
+    if elem.find(':') == Some(1) {
+        format!("0{elem}")
+    } else {
+        elem.to_string()
+    }
+}
+
+/// Null and infinity elements of a temporal vector only carry their type suffix once for the
+/// whole literal, so put it back for `from_literal`
+fn typed(elem: &str, suffix: char) -> String {
+    if is_special(elem) {
+        format!("{elem}{suffix}")
+    } else {
+        elem.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str) -> Vec<Expr> {
+        Parser::new(input).parse().unwrap()
+    }
+
+    fn long(x: i64) -> Expr {
+        Expr::Atom(Q::Long(x))
+    }
+
+    fn name(name: &str) -> Expr {
+        Expr::Identifier(Symbol::from(name))
+    }
+
+    #[test]
+    fn elided_arguments_make_projections() {
+        assert_eq!(
+            parse("+[2;]"),
+            [Expr::Projection {
+                func: Box::new(Expr::Verb(TokenKind::Plus)),
+                args: vec![Some(long(2)), None],
+            }]
+        );
+        assert_eq!(
+            parse("f[;3]"),
+            [Expr::Projection {
+                func: Box::new(name("f")),
+                args: vec![None, Some(long(3))],
+            }]
+        );
+        assert_eq!(
+            parse("f[1;3]"),
+            [Expr::Apply {
+                func: Box::new(name("f")),
+                args: vec![long(1), long(3)],
+            }]
+        );
+    }
+
+    #[test]
+    fn one_item_lists_are_not_atoms() {
+        assert_eq!(Q::Long(1).enlist(), Q::Longs(vec![1]));
+        assert_ne!(Q::Long(1).enlist(), Q::Long(1));
+        assert_eq!(parse_one("enlist 1").unwrap(), Q::Longs(vec![1]));
+        assert_eq!(parse_one(",1").unwrap(), Q::Longs(vec![1]));
+        assert_eq!(parse_one("1").unwrap(), Q::Long(1));
+        assert_eq!(parse_one("(1)").unwrap(), Q::Long(1));
+        assert_eq!(
+            parse_one("enlist 1 2").unwrap(),
+            Q::List(vec![Q::Longs(vec![1, 2])])
+        );
+    }
+
+    #[test]
+    fn empty_parens_and_generic_null() {
+        assert_eq!(parse_one("()").unwrap(), Q::List(vec![]));
+        assert_eq!(parse_one("(::)").unwrap(), Q::Null);
+        assert_eq!(
+            parse_one("(1;;2)").unwrap(),
+            Q::List(vec![Q::Long(1), Q::Null, Q::Long(2)])
+        );
+        assert_eq!(Q::List(vec![]).to_string(), "()");
+        assert_eq!(Q::Null.to_string(), "::");
+    }
+
+    #[test]
+    fn parse_diagnostics_name_their_file() {
+        let err = Parser::new_named("(1;2", "script.q").parse().unwrap_err();
+        let e = err.downcast_ref::<ParseError>().unwrap();
+        assert_eq!(e.src.filename(), Some("script.q"));
+        let err = Parser::new_named("1 \"ab", "script.q").parse().unwrap_err();
+        let e = err.downcast_ref::<lex::StringTerminationError>().unwrap();
+        let mut report = String::new();
+        miette::NarratableReportHandler::new()
+            .render_report(&mut report, e)
+            .unwrap();
+        assert!(report.contains("script.q"), "{report}");
+    }
+
+    #[test]
+    fn empty_typed_vectors() {
+        assert_eq!(parse_one("0#0").unwrap(), Q::Longs(vec![]));
+        assert_eq!(parse_one("0#`").unwrap(), Q::Symbols(vec![]));
+        assert_eq!(parse_one("`long$()").unwrap(), Q::Longs(vec![]));
+        assert_eq!(parse_one("`symbol$()").unwrap(), Q::Symbols(vec![]));
+        assert_eq!(parse_one("\"f\"$()").unwrap(), Q::Floats(vec![]));
+        assert_eq!(Q::empty(7).unwrap().type_id(), 7);
+        assert_eq!(Q::empty(11).unwrap().type_id(), 11);
+        assert_eq!(Q::empty(3), Err(QError::Type));
+    }
+
+    #[test]
+    fn system_directives() {
+        assert_eq!(
+            parse("x:1\nx\n\\\\\ny"),
+            [
+                Expr::Assign {
+                    name: Symbol::from("x"),
+                    value: Box::new(long(1)),
+                },
+                name("x"),
+                Expr::SystemDirective(SystemDirective::Exit),
+            ]
+        );
+        assert_eq!(
+            parse("1\n\\\n2"),
+            [
+                long(1),
+                Expr::SystemDirective(SystemDirective::ToggleK),
+                long(2)
+            ]
+        );
+        assert!(Parser::new("1 \\ 2").parse().is_err());
+    }
+
+    #[test]
+    fn vectors_with_other_separators() {
+        assert_eq!(parse_vector("1,2,3", ',').unwrap(), Q::Longs(vec![1, 2, 3]));
+        assert_eq!(
+            parse_vector("a b c", ' ').unwrap(),
+            Q::Symbols(vec![
+                Symbol::from("a"),
+                Symbol::from("b"),
+                Symbol::from("c")
+            ])
+        );
+        assert_eq!(
+            parse_vector("1,2.5", ',').unwrap(),
+            Q::Floats(vec![1.0, 2.5])
+        );
+        assert_eq!(
+            parse_vector("1,,3", ',').unwrap(),
+            parse_one("1 0N 3").unwrap()
+        );
+        assert!(parse_vector("1,2 3", ',').is_err());
+    }
+
+    #[test]
+    fn lambdas_with_explicit_and_implicit_params() {
+        let add = |x, y| Expr::Apply {
+            func: Box::new(Expr::Verb(TokenKind::Plus)),
+            args: vec![name(x), name(y)],
+        };
+        let params = |names: &[&str]| names.iter().map(|&n| Symbol::from(n)).collect();
+        assert_eq!(
+            parse("{[a;b] a+b}"),
+            [Expr::Lambda {
+                params: params(&["a", "b"]),
+                body: vec![add("a", "b")],
+            }]
+        );
+        assert_eq!(
+            parse("{x+y}"),
+            [Expr::Lambda {
+                params: params(&["x", "y"]),
+                body: vec![add("x", "y")],
+            }]
+        );
+        assert_eq!(
+            parse("{[a] b:a; b}"),
+            [Expr::Lambda {
+                params: params(&["a"]),
+                body: vec![
+                    Expr::Assign {
+                        name: Symbol::from("b"),
+                        value: Box::new(name("a")),
+                    },
+                    name("b"),
+                ],
+            }]
+        );
+    }
+
+    #[test]
+    fn implicit_params_go_up_to_the_highest_used() {
+        let params = |input| match parse(input).as_slice() {
+            [Expr::Lambda { params, .. }] => params
+                .iter()
+                .map(|p| p.resolve().to_string())
+                .collect::<Vec<_>>(),
+            exprs => panic!("not a lambda: {exprs:?}"),
+        };
+        assert_eq!(params("{x+z}"), ["x", "y", "z"]);
+        assert_eq!(params("{x}"), ["x"]);
+        assert_eq!(params("{1}"), ["x"]);
+        assert_eq!(params("{y:1; y}"), ["x", "y"]);
+        // a nested lambda's params are its own
+        assert_eq!(params("{x+{z}}"), ["x"]);
+    }
+
+    #[test]
+    fn literals_evaluate_to_their_values() {
+        let sym = |s: &str| Symbol::from(s);
+        let hour = 3_600_000_000_000;
+        // guids, enumerations and attributes have no literal syntax, so every other variant
+        // appears here
+        let cases = [
+            ("1b", Q::Boolean(true)),
+            ("0x2a", Q::Byte(42)),
+            ("42h", Q::Short(42)),
+            ("42i", Q::Int(42)),
+            ("42", Q::Long(42)),
+            ("1.5e", Q::Real(1.5)),
+            ("1.5", Q::Float(1.5)),
+            ("\"a\"", Q::Char(b'a')),
+            ("`a", Q::Symbol(sym("a"))),
+            ("2000.01.01D01:00", Q::Timestamp(Timestamp::from_i64(hour))),
+            ("2000.02m", Q::Month(Month::from_i32(1))),
+            ("2000.01.02", Q::Date(Date::from_i32(1))),
+            ("0D01:00", Q::Timespan(Timespan::from_i64(hour))),
+            ("01:00", Q::Minute(Minute::from_i32(60))),
+            ("00:01:00", Q::Second(Second::from_i32(60))),
+            ("101b", Q::Booleans(vec![true, false, true])),
+            ("0x0102", Q::Bytes(vec![1, 2])),
+            ("1 2h", Q::Shorts(vec![1, 2])),
+            ("1 2i", Q::Ints(vec![1, 2])),
+            ("1 2", Q::Longs(vec![1, 2])),
+            ("1 2e", Q::Reals(vec![1.0, 2.0])),
+            ("1 2.5", Q::Floats(vec![1.0, 2.5])),
+            ("\"ab\"", Q::String(b"ab".to_vec())),
+            ("`a`b", Q::Symbols(vec![sym("a"), sym("b")])),
+            (
+                "2000.01.01D00:00 2000.01.01D01:00",
+                Q::Timestamps(vec![Timestamp::from_i64(0), Timestamp::from_i64(hour)]),
+            ),
+            (
+                "2000.01 2000.02m",
+                Q::Months(vec![Month::from_i32(0), Month::from_i32(1)]),
+            ),
+            (
+                "2000.01.01 2000.01.02",
+                Q::Dates(vec![Date::from_i32(0), Date::from_i32(1)]),
+            ),
+            (
+                "0D00:00 0D01:00",
+                Q::Timespans(vec![Timespan::from_i64(0), Timespan::from_i64(hour)]),
+            ),
+            (
+                "00:00 01:00",
+                Q::Minutes(vec![Minute::from_i32(0), Minute::from_i32(60)]),
+            ),
+            (
+                "00:00:00 00:01:00",
+                Q::Seconds(vec![Second::from_i32(0), Second::from_i32(60)]),
+            ),
+            ("`long$()", Q::Longs(vec![])),
+            ("enlist 1", Q::Longs(vec![1])),
+            ("()", Q::List(vec![])),
+            ("(1;`a)", Q::List(vec![Q::Long(1), Q::Symbol(sym("a"))])),
+            (
+                "(1 2;3 4)",
+                Q::List(vec![Q::Longs(vec![1, 2]), Q::Longs(vec![3, 4])]),
+            ),
+            (
+                "`a`b!1 2",
+                Q::Dict(
+                    Box::new(Q::Symbols(vec![sym("a"), sym("b")])),
+                    Box::new(Q::Longs(vec![1, 2])),
+                ),
+            ),
+            (
+                "([] a:1 2)",
+                Q::Table(vec![sym("a")], vec![Q::Longs(vec![1, 2])]),
+            ),
+            (
+                "flip `a`b!(1 2;3 4)",
+                Q::Table(
+                    vec![sym("a"), sym("b")],
+                    vec![Q::Longs(vec![1, 2]), Q::Longs(vec![3, 4])],
+                ),
+            ),
+            (
+                "([k:`x`y] v:1 2)",
+                Q::Dict(
+                    Box::new(Q::Table(
+                        vec![sym("k")],
+                        vec![Q::Symbols(vec![sym("x"), sym("y")])],
+                    )),
+                    Box::new(Q::Table(vec![sym("v")], vec![Q::Longs(vec![1, 2])])),
+                ),
+            ),
+            ("(::)", Q::Null),
+            ("0N", Q::Long(i64::MIN)),
+            ("0Nh", Q::Short(i16::MIN)),
+            ("0Nd", Q::Date(Date::NULL)),
+            ("0Np", Q::Timestamp(Timestamp::NULL)),
+            ("0N 1", Q::Longs(vec![i64::MIN, 1])),
+            ("0W", Q::Long(i64::MAX)),
+            ("-0W", Q::Long(-i64::MAX)),
+            ("0Wi", Q::Int(i32::MAX)),
+            ("0w -0w", Q::Floats(vec![f64::INFINITY, f64::NEG_INFINITY])),
+            ("0Wd", Q::Date(Date::INF)),
+            ("-0Wp", Q::Timestamp(Timestamp::NEG_INF)),
+            ("0Wn", Q::Timespan(Timespan::INF)),
+            ("0Wu", Q::Minute(Minute::INF)),
+            ("0Wv", Q::Second(Second::INF)),
+            ("0Wm", Q::Month(Month::INF)),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(parse_one(input).unwrap(), expected, "{input}");
+        }
+    }
+
+    #[test]
+    fn nested_lists_stay_general() {
+        assert_eq!(
+            parse_one("(1 2;3 4 5)").unwrap(),
+            Q::List(vec![Q::Longs(vec![1, 2]), Q::Longs(vec![3, 4, 5])])
+        );
+        assert_eq!(
+            parse_one("(1 2;3 4)").unwrap(),
+            Q::List(vec![Q::Longs(vec![1, 2]), Q::Longs(vec![3, 4])])
+        );
+        assert_eq!(
+            parse_one("(1 2;`a`b)").unwrap(),
+            Q::List(vec![
+                Q::Longs(vec![1, 2]),
+                Q::Symbols(vec![Symbol::from("a"), Symbol::from("b")])
+            ])
+        );
+        assert_eq!(parse_one("(1;2)").unwrap(), Q::Longs(vec![1, 2]));
+    }
+
+    #[test]
+    fn inline_tables() {
+        let sym = |s: &str| Symbol::from(s);
+        assert_eq!(
+            parse_one("([] sym:`a`b; px:1 2)").unwrap(),
+            Q::Table(
+                vec![sym("sym"), sym("px")],
+                vec![Q::Symbols(vec![sym("a"), sym("b")]), Q::Longs(vec![1, 2])],
+            )
+        );
+        assert_eq!(
+            parse_one("([sym:`a`b] px:1 2; n:0)").unwrap(),
+            Q::Dict(
+                Box::new(Q::Table(
+                    vec![sym("sym")],
+                    vec![Q::Symbols(vec![sym("a"), sym("b")])]
+                )),
+                Box::new(Q::Table(
+                    vec![sym("px"), sym("n")],
+                    vec![Q::Longs(vec![1, 2]), Q::Longs(vec![0, 0])],
+                )),
+            )
+        );
+        // a column that is not a literal is left to evaluation
+        assert_eq!(
+            parse("([] c; px:1)"),
+            [Expr::Table {
+                keys: vec![],
+                columns: vec![(sym("c"), name("c")), (sym("px"), long(1))],
+            }]
+        );
+        for mismatched in ["([] a:1 2; b:1 2 3)", "([k:1 2] v:1 2 3)"] {
+            let err = Parser::new(mismatched).parse().unwrap_err();
+            let err = err.downcast_ref::<ParseError>().unwrap();
+            assert_eq!(err.message, "Columns of different counts", "{mismatched}");
+        }
+    }
+
+    #[test]
+    fn date_literals() {
+        assert_eq!(parse_one("2000.01.01").unwrap(), Q::Date(Date::from_i32(0)));
+        assert_eq!(parse_one("9999.12.31").unwrap(), Q::Date(Date::MAX));
+        let err = Parser::new("x: 2000.13.01").parse().unwrap_err();
+        let err = err.downcast_ref::<InvalidLiteralError>().unwrap();
+        assert_eq!(err.literal, "2000.13.01");
+        assert_eq!((err.err_span.offset(), err.err_span.len()), (3, 10));
+    }
+
+    #[test]
+    fn empty_statements_are_skipped() {
+        let longs = || Expr::Vector(Q::Longs(vec![1, 2, 3]));
+        assert_eq!(parse("1 2 3;"), [longs()]);
+        assert_eq!(parse("  1 2 3  "), [longs()]);
+        assert_eq!(parse(";1;"), [long(1)]);
+        assert_eq!(parse("1;;2"), [long(1), long(2)]);
+        assert_eq!(parse(";"), []);
+        assert_eq!(parse_one("1 2 3;").unwrap(), Q::Longs(vec![1, 2, 3]));
+        let err = parse_one(";").unwrap_err();
+        let err = err.downcast_ref::<ParseError>().unwrap();
+        assert_eq!(err.message, "Expected a literal value, found none");
+    }
+
+    #[test]
+    fn timestamp_literals() {
+        const HOUR: i64 = 3_600_000_000_000;
+        let ts = |s: &str| match parse_one(s).unwrap() {
+            Q::Timestamp(t) => t,
+            other => panic!("expected a timestamp, got {other:?}"),
+        };
+        assert_eq!(
+            ts("2000.01.01D12:00:00.000000000"),
+            Timestamp::from_i64(12 * HOUR)
+        );
+        assert_eq!(
+            ts("2000.01.01D12:34:56.123456789"),
+            Timestamp::from_i64(45_296_123_456_789)
+        );
+        assert_eq!(ts("2000.01.01D12:00:00"), Timestamp::from_i64(12 * HOUR));
+        assert_eq!(
+            ts("2000.01.01D12:00:00.5"),
+            Timestamp::from_i64(12 * HOUR + 500_000_000)
+        );
+        assert_eq!(ts("2000.01.02D"), Timestamp::from_i64(24 * HOUR));
+        assert_eq!(
+            Lexer::new("2000.01.01D12:00:00")
+                .next()
+                .unwrap()
+                .unwrap()
+                .kind,
+            TokenKind::Single(Atomic::Timestamp)
+        );
+    }
+
+    #[test]
+    fn timespan_literals() {
+        const DAY: i64 = 86_400_000_000_000;
+        assert_eq!(
+            parse_one("1D12:00:00.000000000").unwrap(),
+            Q::Timespan(Timespan::from_i64(DAY + DAY / 2))
+        );
+        assert_eq!(
+            parse_one("-2D00:00:00").unwrap(),
+            Q::Timespan(Timespan::from_i64(-2 * DAY))
+        );
+        assert_eq!(
+            parse_one("0D00:00:01.5").unwrap(),
+            Q::Timespan(Timespan::from_i64(1_500_000_000))
+        );
+        assert_eq!(
+            Lexer::new("-2D00:00:00").next().unwrap().unwrap().kind,
+            TokenKind::Single(Atomic::Timespan)
+        );
+    }
 }
@@ -0,0 +1,284 @@
+// Aggregations reducing a list to an atom, and their grouped application with `fby`
+use crate::qtype::list::group_items;
+use crate::qtype::math::{avg, extreme, sum};
+use crate::qtype::symbol::Symbol;
+use crate::qtype::{Q, QError};
+use std::cmp::Ordering;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aggregate {
+    Sum,
+    Avg,
+    Max,
+    Min,
+}
+
+impl Aggregate {
+    /// Reduces a numeric vector to an atom, ignoring nulls. `Sum` keeps the type of the
+    /// vector and is zero when empty, `Avg` is a float, and `Max`/`Min` of no items are null.
+    pub fn apply(&self, list: &Q) -> Result<Q, QError> {
+        macro_rules! reduce {
+            ($($vector:ident => $atom:ident),*) => {
+                match list.without_attr_ref() {
+                    $(
+                        Q::$vector(v) => match self {
+                            Aggregate::Sum => Q::$atom(sum(v)),
+                            Aggregate::Avg => Q::Float(avg(v)),
+                            Aggregate::Max => Q::$atom(extreme(v, Ordering::Greater)),
+                            Aggregate::Min => Q::$atom(extreme(v, Ordering::Less)),
+                        },
+                    )*
+                    _ => return Err(QError::Type),
+                }
+            };
+        }
+        Ok(reduce!(
+            Shorts => Short,
+            Ints => Int,
+            Longs => Long,
+            Reals => Real,
+            Floats => Float
+        ))
+    }
+}
+
+impl Q {
+    /// q's `(agg;x) fby group`: each item of `self` replaced by the aggregate of the items in
+    /// its group, where items are grouped by the matching item of `group`
+    pub fn fby(&self, agg: Aggregate, group: &Q) -> Result<Q, QError> {
+        let keys = group.items()?;
+        if keys.len() != self.len() {
+            return Err(QError::Length);
+        }
+        let (firsts, row_group) = group_items(&keys);
+        let mut members: Vec<Vec<usize>> = vec![Vec::new(); firsts.len()];
+        for (i, &g) in row_group.iter().enumerate() {
+            members[g].push(i);
+        }
+        let aggregates = members
+            .iter()
+            .map(|idx| agg.apply(&self.reorder(idx, |_| false)?))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Q::collapse(
+            row_group.iter().map(|&g| aggregates[g].clone()).collect(),
+        ))
+    }
+}
+
+impl Q {
+    /// A keyed table of `value` aggregated by `agg` for each distinct `rows` (the key) and
+    /// `cols` pair, with a column named after each distinct `cols` symbol. Both appear in
+    /// order of first appearance, and pairs with no rows get the typed null, as in q's
+    /// `exec cols#(distinct cols)!value by rows from t`.
+    pub fn pivot(
+        &self,
+        rows: Symbol,
+        cols: Symbol,
+        value: Symbol,
+        agg: Aggregate,
+    ) -> Result<Q, QError> {
+        if !matches!(self, Q::Table(..)) {
+            return Err(QError::Type);
+        }
+        let column = |name: &Symbol| self.column(name).ok_or(QError::Domain);
+        let (row_column, pivot_column, values) = (column(&rows)?, column(&cols)?, column(&value)?);
+        let (row_firsts, row_group) = group_items(&row_column.items()?);
+        let (col_firsts, col_group) = group_items(&pivot_column.items()?);
+        let names = match pivot_column.reorder(&col_firsts, |_| false)? {
+            Q::Symbols(names) => names,
+            _ => return Err(QError::Type),
+        };
+        let null = agg.apply(&values.reorder(&[], |_| false)?)?.null_item();
+        // the rows of each cell, gathered in one pass
+        let mut cells: Vec<Vec<Vec<usize>>> =
+            vec![vec![Vec::new(); row_firsts.len()]; col_firsts.len()];
+        for (i, (&r, &c)) in row_group.iter().zip(&col_group).enumerate() {
+            cells[c][r].push(i);
+        }
+        let pivoted = cells
+            .iter()
+            .map(|column| {
+                let column = column
+                    .iter()
+                    .map(|idx| match idx.as_slice() {
+                        [] => Ok(null.clone()),
+                        idx => agg.apply(&values.reorder(idx, |_| false)?),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(Q::collapse(column))
+            })
+            .collect::<Result<Vec<_>, QError>>()?;
+        let key = Q::table(
+            vec![rows],
+            vec![row_column.reorder(&row_firsts, |_| false)?],
+        )?;
+        Ok(Q::Dict(Box::new(key), Box::new(Q::table(names, pivoted)?)))
+    }
+
+    /// q's `count distinct x`: the number of distinct items of `self`, the values of a
+    /// dictionary or the rows of a table
+    pub fn count_distinct(&self) -> usize {
+        self.frequencies().len()
+    }
+
+    /// The most frequent item of `self`, the first to appear among equally frequent ones, or
+    /// the typed null when `self` is empty
+    pub fn mode(&self) -> Q {
+        self.frequencies()
+            .into_iter()
+            .rev()
+            .max_by_key(|(_, count)| *count)
+            .map_or_else(|| self.null_item(), |(item, _)| item)
+    }
+
+    /// Summary statistics of a table, like q's `.Q.summary`: a keyed table from each column
+    /// name `c` to its `count`, `nulls` and `distinct` counts, and for numeric columns its
+    /// `min`, `max` and `avg`. Other columns have the generic null for those and a null `avg`.
+    pub fn describe(&self) -> Result<Q, QError> {
+        let Q::Table(columns, values) = self else {
+            return Err(QError::Type);
+        };
+        let count = |f: fn(&Q) -> usize| Q::Longs(values.iter().map(|v| f(v) as i64).collect());
+        let stat = |agg: Aggregate| -> Result<Vec<Q>, QError> {
+            values
+                .iter()
+                .map(|v| match v.type_id() {
+                    5..=9 => agg.apply(v),
+                    _ if agg == Aggregate::Avg => Ok(Q::Float(f64::NAN)),
+                    _ => Ok(Q::Null),
+                })
+                .collect()
+        };
+        let key = Q::table(vec![Symbol::from("c")], vec![Q::Symbols(columns.clone())])?;
+        let value = Q::table(
+            ["count", "nulls", "distinct", "min", "max", "avg"]
+                .into_iter()
+                .map(Symbol::from)
+                .collect(),
+            vec![
+                count(Q::len),
+                count(Q::null_count),
+                count(Q::count_distinct),
+                Q::List(stat(Aggregate::Min)?),
+                Q::List(stat(Aggregate::Max)?),
+                Q::collapse(stat(Aggregate::Avg)?),
+            ],
+        )?;
+        Ok(Q::Dict(Box::new(key), Box::new(value)))
+    }
+
+    // Helper methods
+    /// The distinct items of `self` in order of first appearance, each with its count
+    fn frequencies(&self) -> Vec<(Q, usize)> {
+        let items = match self {
+            Q::Dict(_, values) => values.items(),
+            Q::Table(..) => (0..self.len()).map(|i| self.row(i)).collect(),
+            list => list.items(),
+        }
+        .unwrap_or_else(|_| vec![self.clone()]);
+        let (firsts, group) = group_items(&items);
+        let mut counts = vec![0; firsts.len()];
+        for g in group {
+            counts[g] += 1;
+        }
+        firsts
+            .iter()
+            .map(|&i| items[i].clone())
+            .zip(counts)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn q(input: &str) -> Q {
+        crate::parse_one(input).unwrap()
+    }
+
+    #[test]
+    fn fby_broadcasts_group_aggregates() {
+        let (data, grp) = (q("1 2 3 4 5"), q("`a`b`a`b`a"));
+        assert_eq!(data.fby(Aggregate::Sum, &grp), Ok(q("9 6 9 6 9")));
+        assert_eq!(data.fby(Aggregate::Max, &grp), Ok(q("5 4 5 4 5")));
+        assert_eq!(data.fby(Aggregate::Avg, &grp), Ok(q("3 3 3 3 3f")));
+        assert_eq!(data.fby(Aggregate::Sum, &q("`a`b")), Err(QError::Length));
+    }
+
+    #[test]
+    fn count_distinct_and_mode() {
+        assert_eq!(q("1 2 2 3").count_distinct(), 3);
+        assert_eq!(q("1 2 2 3 3 3").mode(), q("3"));
+        // ties go to the first seen
+        assert_eq!(q("`b`a`a`b").mode(), q("`b"));
+        assert_eq!(q("`long$()").mode(), q("0N"));
+        assert_eq!(q("([] a:1 1 2)").count_distinct(), 2);
+        // rows with float nulls match, as null floats do
+        assert_eq!(q("([] a:0n 0n)").count_distinct(), 1);
+        assert_eq!(q("([] a:0n 0n 1f; b:`x`x`x)").count_distinct(), 2);
+        assert_eq!(
+            q("([] a:1 0n 0n)").mode().to_string(),
+            q("(enlist `a)!enlist 0n").to_string()
+        );
+    }
+
+    #[test]
+    fn describe_summarizes_each_column() {
+        let t = q("([] a:1 2 0N; s:`x`y`x)");
+        let Ok(Q::Dict(key, value)) = t.describe() else {
+            panic!("not a keyed table");
+        };
+        assert_eq!(*key, q("([] c:`a`s)"));
+        let stat = |name| value.column(&Symbol::from(name)).unwrap().clone();
+        assert_eq!(stat("count"), q("3 3"));
+        assert_eq!(stat("nulls"), q("1 0"));
+        assert_eq!(stat("distinct"), q("3 2"));
+        assert_eq!(stat("min"), Q::List(vec![Q::Long(1), Q::Null]));
+        assert_eq!(stat("max"), Q::List(vec![Q::Long(2), Q::Null]));
+        // NaN is not equal to itself, so compare as shown
+        assert_eq!(stat("avg").to_string(), q("1.5 0n").to_string());
+        assert_eq!(q("1 2").describe(), Err(QError::Type));
+    }
+
+    #[test]
+    fn pivot_spreads_symbols_into_columns() {
+        let t =
+            q("([] date:2000.01.01 2000.01.01 2000.01.02 2000.01.01; sym:`a`b`b`a; px:1 2 3 4)");
+        let pivot = |agg| {
+            t.pivot(
+                Symbol::from("date"),
+                Symbol::from("sym"),
+                Symbol::from("px"),
+                agg,
+            )
+        };
+        let key = q("([] date:2000.01.01 2000.01.02)");
+        // there is no `a on the second date, so that cell is a typed null
+        let keyed = |value| Q::Dict(Box::new(key.clone()), Box::new(value));
+        assert_eq!(pivot(Aggregate::Sum), Ok(keyed(q("([] a:5 0N; b:2 3)"))));
+        assert_eq!(pivot(Aggregate::Max), Ok(keyed(q("([] a:4 0N; b:2 3)"))));
+        assert_eq!(
+            pivot(Aggregate::Avg).unwrap().to_string(),
+            keyed(q("([] a:2.5 0n; b:2 3f)")).to_string()
+        );
+        assert_eq!(
+            t.pivot(
+                Symbol::from("date"),
+                Symbol::from("nope"),
+                Symbol::from("px"),
+                Aggregate::Sum
+            ),
+            Err(QError::Domain)
+        );
+        assert_eq!(
+            q("1 2").pivot(
+                Symbol::from("a"),
+                Symbol::from("b"),
+                Symbol::from("c"),
+                Aggregate::Sum
+            ),
+            Err(QError::Type)
+        );
+    }
+}
@@ -0,0 +1,85 @@
+// Attributes on q lists, i.e. `s#`, `u#`, `p#` and `g#`
+use crate::qtype::{Q, QError};
+use std::collections::HashSet;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Attr {
+    Sorted,
+    Unique,
+    Parted,
+    Grouped,
+}
+
+impl Attr {
+    pub fn code(&self) -> char {
+        match self {
+            Attr::Sorted => 's',
+            Attr::Unique => 'u',
+            Attr::Parted => 'p',
+            Attr::Grouped => 'g',
+        }
+    }
+
+    pub fn from_code(c: char) -> Option<Attr> {
+        match c {
+            's' => Some(Attr::Sorted),
+            'u' => Some(Attr::Unique),
+            'p' => Some(Attr::Parted),
+            'g' => Some(Attr::Grouped),
+            _ => None,
+        }
+    }
+
+    /// Whether `list` satisfies the attribute. `g#` is a lookup index rather than a property
+    /// of the items, so it holds for any list.
+    pub fn holds(&self, list: &Q) -> Result<bool, QError> {
+        if matches!(self, Attr::Grouped) {
+            return Ok(true);
+        }
+        let ranks = match list.rank_dense()? {
+            Q::Longs(ranks) => ranks,
+            _ => unreachable!(),
+        };
+        Ok(match self {
+            Attr::Sorted => ranks.is_sorted(),
+            Attr::Unique => ranks.iter().collect::<HashSet<_>>().len() == ranks.len(),
+            Attr::Parted => {
+                // every run of equal items must be the only one of its value
+                let mut seen = HashSet::new();
+                ranks.chunk_by(|a, b| a == b).all(|run| seen.insert(run[0]))
+            }
+            Attr::Grouped => true,
+        })
+    }
+}
+
+impl Q {
+    /// q's `#` applying an attribute, which fails with `'s-fail` etc. when `self` does not
+    /// satisfy it. Any existing attribute is replaced.
+    pub fn with_attr(self, attr: Attr) -> Result<Q, QError> {
+        let list = self.without_attr();
+        if list.is_atom() || matches!(list, Q::Dict(..) | Q::Table(..)) {
+            return Err(QError::Type);
+        }
+        if !attr.holds(&list)? {
+            return Err(QError::Fail(attr));
+        }
+        Ok(Q::Attributed(attr, Box::new(list)))
+    }
+
+    /// q's `` `#x ``: `self` with any attribute removed
+    pub fn without_attr(self) -> Q {
+        match self {
+            Q::Attributed(_, list) => *list,
+            other => other,
+        }
+    }
+
+    /// q's `attr`
+    pub fn attr(&self) -> Option<Attr> {
+        match self {
+            Q::Attributed(attr, _) => Some(*attr),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,107 @@
+// Conversions between q types
+use crate::qtype::chrono::{Date, Minute, Month, Second, Timespan, Timestamp};
+use crate::qtype::{Q, QError};
+
+impl Q {
+    /// The items of `self` reinterpreted as the type `target` of the same width, keeping the
+    /// underlying ints rather than converting values, e.g. longs as nanoseconds since 2000 for
+    /// timestamps. Longs, timestamps and timespans are 64-bit; ints, months, dates, minutes and
+    /// seconds are 32-bit. The sign of `target` is ignored, so atoms stay atoms.
+    pub fn reinterpret(&self, target: i8) -> Result<Q, QError> {
+        let target = target.unsigned_abs();
+        let source = self.without_attr_ref();
+        if let Some(raw) = longs(source) {
+            let atom = source.is_atom();
+            let q = match target {
+                7 => Q::Longs(raw),
+                12 => Q::Timestamps(raw.into_iter().map(Timestamp::from_i64).collect()),
+                16 => Q::Timespans(raw.into_iter().map(Timespan::from_i64).collect()),
+                _ => return Err(QError::Type),
+            };
+            return if atom {
+                q.at(0).ok_or(QError::Length)
+            } else {
+                Ok(q)
+            };
+        }
+        if let Some(raw) = ints(source) {
+            let atom = source.is_atom();
+            let wrap = |f: fn(i32) -> Option<Q>| {
+                raw.iter()
+                    .map(|&x| f(x).ok_or(QError::Domain))
+                    .collect::<Result<Vec<_>, _>>()
+            };
+            let q = match target {
+                6 => Q::Ints(raw.clone()),
+                13 => Q::collapse(wrap(|x| Month::from_raw(x).map(Q::Month))?),
+                14 => Q::collapse(wrap(|x| Date::from_raw(x).map(Q::Date))?),
+                17 => Q::collapse(wrap(|x| Minute::from_raw(x).map(Q::Minute))?),
+                18 => Q::collapse(wrap(|x| Second::from_raw(x).map(Q::Second))?),
+                _ => return Err(QError::Type),
+            };
+            if raw.is_empty() {
+                return Q::empty(target as i8);
+            }
+            return if atom {
+                q.at(0).ok_or(QError::Length)
+            } else {
+                Ok(q)
+            };
+        }
+        Err(QError::Type)
+    }
+}
+
+/// The underlying ints of a 64-bit integer or temporal atom or vector
+fn longs(q: &Q) -> Option<Vec<i64>> {
+    Some(match q {
+        Q::Long(x) => vec![*x],
+        Q::Timestamp(t) => vec![t.to_i64()],
+        Q::Timespan(t) => vec![t.to_i64()],
+        Q::Longs(v) => v.clone(),
+        Q::Timestamps(v) => v.iter().map(|t| t.to_i64()).collect(),
+        Q::Timespans(v) => v.iter().map(|t| t.to_i64()).collect(),
+        _ => return None,
+    })
+}
+
+/// The underlying ints of a 32-bit integer or temporal atom or vector
+fn ints(q: &Q) -> Option<Vec<i32>> {
+    Some(match q {
+        Q::Int(x) => vec![*x],
+        Q::Month(m) => vec![m.to_i32()],
+        Q::Date(d) => vec![d.to_i32()],
+        Q::Minute(m) => vec![m.to_i32()],
+        Q::Second(s) => vec![s.to_i32()],
+        Q::Ints(v) => v.clone(),
+        Q::Months(v) => v.iter().map(|m| m.to_i32()).collect(),
+        Q::Dates(v) => v.iter().map(|d| d.to_i32()).collect(),
+        Q::Minutes(v) => v.iter().map(|m| m.to_i32()).collect(),
+        Q::Seconds(v) => v.iter().map(|s| s.to_i32()).collect(),
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn q(input: &str) -> Q {
+        crate::parse_one(input).unwrap()
+    }
+
+    #[test]
+    fn reinterpret_keeps_the_underlying_ints() {
+        let day = 86_400_000_000_000;
+        assert_eq!(
+            Q::Longs(vec![0, day]).reinterpret(12),
+            Ok(q("2000.01.01D00:00 2000.01.02D00:00"))
+        );
+        assert_eq!(Q::Long(day).reinterpret(-12), Ok(q("2000.01.02D00:00")));
+        assert_eq!(q("2000.01.02D00:00").reinterpret(7), Ok(Q::Long(day)));
+        assert_eq!(q("0 1i").reinterpret(14), Ok(q("2000.01.01 2000.01.02")));
+        assert_eq!(q("0N 1i").reinterpret(17), Ok(q("0N 00:01u")));
+        assert_eq!(q("0 1").reinterpret(14), Err(QError::Type));
+        assert_eq!(q("`int$()").reinterpret(14), Ok(q("`date$()")));
+    }
+}
@@ -11,8 +11,8 @@ pub struct Date {
 }
 
 impl Date {
-    //TODO: in q, there're actually two special values of date: 0000.00.00 (stands for all values out of range), and 0Wd (infinite)
-    // We use `assert!` to handle these cases for now. These special values will be added later.
+    //TODO: in q, 0000.00.00 is a special date value standing for all values out of range
+    // We use `assert!` to handle this case for now. It will be added later.
     const MAX_DAYS: i32 = 2921939;
     const MIN_DAYS: i32 = -730119;
     pub const MAX: Date = Date {
@@ -21,21 +21,56 @@ impl Date {
     pub const MIN: Date = Date {
         days: Date::MIN_DAYS,
     }; // 0001.01.01
+    pub const NULL: Date = Date { days: i32::MIN }; // 0Nd
+    pub const INF: Date = Date { days: i32::MAX }; // 0Wd
+    pub const NEG_INF: Date = Date { days: -i32::MAX }; // -0Wd
     const EPOCH: NaiveDate = NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
 
     /// Creates a Date from a literal string in format "YYYY.MM.DD"
     pub fn from_literal(literal: &str) -> Result<Self, String> {
+        match literal {
+            "0Nd" => return Ok(Date::NULL),
+            "0Wd" => return Ok(Date::INF),
+            "-0Wd" => return Ok(Date::NEG_INF),
+            _ => {}
+        }
         let date =
             NaiveDate::parse_from_str(literal, "%Y.%m.%d").map_err(|_| format!("'{literal}"))?;
 
-        let days = date.signed_duration_since(Date::EPOCH).num_days() as i32;
+        let days = date.signed_duration_since(Date::EPOCH).num_days();
+        if !(Date::MIN_DAYS as i64..=Date::MAX_DAYS as i64).contains(&days) {
+            return Err(format!("'{literal}"));
+        }
+        Ok(Date { days: days as i32 })
+    }
+
+    /// Like `from_literal`, but also accepts "YYYY/MM/DD" and "YYYY-MM-DD", as seen outside
+    /// of q. Both separators must be the same.
+    pub fn from_literal_flexible(literal: &str) -> Result<Self, String> {
+        let mut separators = literal.chars().filter(|c| matches!(c, '.' | '/' | '-'));
+        match (separators.next(), separators.next(), separators.next()) {
+            (Some(a), Some(b), None) if a == b => Date::from_literal(&literal.replace(a, ".")),
+            _ => Err(format!("'{literal}")),
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        self.days == i32::MIN
+    }
 
-        assert!((Date::MIN_DAYS..Date::MAX_DAYS).contains(&days));
-        Ok(Date { days })
+    pub fn is_inf(&self) -> bool {
+        self.days == i32::MAX || self.days == -i32::MAX
     }
 
     /// Converts the Date to a literal string in format "YYYY.MM.DD"
     pub fn to_literal(self) -> String {
+        if self.is_null() {
+            return "0Nd".to_string();
+        }
+        if self.is_inf() {
+            let sign = if self.days < 0 { "-" } else { "" };
+            return format!("{sign}0Wd");
+        }
         let date = self.to_naive_date();
         format!("{:04}.{:02}.{:02}", date.year(), date.month(), date.day())
     }
@@ -59,7 +94,7 @@ impl Date {
     }
 
     pub fn from_i32(days: i32) -> Self {
-        assert!((Date::MIN_DAYS..Date::MAX_DAYS).contains(&days));
+        assert!((Date::MIN_DAYS..=Date::MAX_DAYS).contains(&days));
         Date { days }
     }
 
@@ -67,6 +102,19 @@ impl Date {
         self.days
     }
 
+    /// Each day from `self` up to and including `end`, which is empty if `end` is earlier
+    /// or either is null or infinite
+    pub fn iter_to(self, end: Date) -> impl Iterator<Item = Date> {
+        let special = |x: Date| x.is_null() || x.is_inf();
+        // an empty range, as from 1 to 0, for special endpoints
+        let (first, last) = if special(self) || special(end) {
+            (1, 0)
+        } else {
+            (self.days, end.days)
+        };
+        (first..=last).map(Date::from_i32)
+    }
+
     // Helper methods
     fn to_naive_date(self) -> NaiveDate {
         Date::EPOCH + Duration::days(self.days as i64)
@@ -80,7 +128,7 @@ impl Date {
 
 impl From<i32> for Date {
     fn from(days: i32) -> Self {
-        assert!((Date::MIN_DAYS..Date::MAX_DAYS).contains(&days));
+        assert!((Date::MIN_DAYS..=Date::MAX_DAYS).contains(&days));
         Date { days }
     }
 }
@@ -125,6 +173,10 @@ impl Add<i32> for Date {
     type Output = Date;
 
     fn add(self, rhs: i32) -> Date {
+        // nulls and infinities absorb the offset
+        if self.is_null() || self.is_inf() {
+            return self;
+        }
         Date {
             days: self.to_i32() + rhs,
         }
@@ -135,6 +187,9 @@ impl Add<Date> for i32 {
     type Output = Date;
 
     fn add(self, rhs: Date) -> Date {
+        if rhs.is_null() || rhs.is_inf() {
+            return rhs;
+        }
         Date {
             days: self + rhs.to_i32(),
         }
@@ -145,6 +200,10 @@ impl Sub<i32> for Date {
     type Output = Date;
 
     fn sub(self, rhs: i32) -> Date {
+        // nulls and infinities absorb the offset
+        if self.is_null() || self.is_inf() {
+            return self;
+        }
         Date {
             days: self.to_i32() - rhs,
         }
@@ -155,6 +214,12 @@ impl Sub<Date> for i32 {
     type Output = Date;
 
     fn sub(self, rhs: Date) -> Date {
+        if rhs.is_null() {
+            return rhs;
+        }
+        if rhs.is_inf() {
+            return Date { days: -rhs.days };
+        }
         Date {
             days: self - rhs.to_i32(),
         }
@@ -174,6 +239,15 @@ impl Timestamp {
     pub const MIN: Timestamp = Timestamp {
         nanoseconds: Timestamp::MIN_NANO,
     };
+    pub const NULL: Timestamp = Timestamp {
+        nanoseconds: i64::MIN,
+    }; // 0Np
+    pub const INF: Timestamp = Timestamp {
+        nanoseconds: i64::MAX,
+    }; // 0Wp
+    pub const NEG_INF: Timestamp = Timestamp {
+        nanoseconds: -i64::MAX,
+    }; // -0Wp
     pub const MAX: Timestamp = Timestamp {
         nanoseconds: Timestamp::MAX_NANO,
     };
@@ -190,20 +264,44 @@ impl Timestamp {
         .and_hms_nano_opt(23, 47, 16, 854775806)
         .unwrap();
 
-    fn from_literal(literal: &str) -> Result<Self, String> {
+    pub fn from_literal(literal: &str) -> Result<Self, String> {
+        match literal {
+            "0Np" => return Ok(Timestamp::NULL),
+            "0Wp" => return Ok(Timestamp::INF),
+            "-0Wp" => return Ok(Timestamp::NEG_INF),
+            _ => {}
+        }
         let dt = NaiveDateTime::parse_from_str(literal, "%Y.%m.%dD%H:%M:%S%.9f")
             .map_err(|_| format!("'{literal}"))?;
 
+        if !(Timestamp::MIN_NAIVE_DATE_TIME..=Timestamp::MAX_NAIVE_DATE_TIME).contains(&dt) {
+            return Err(format!("'{literal}"));
+        }
+
         let nanoseconds = dt
             .signed_duration_since(Timestamp::EPOCH)
             .num_nanoseconds()
-            .unwrap();
-
-        assert!((Timestamp::MIN_NANO..Timestamp::MAX_NANO).contains(&nanoseconds));
+            .filter(|n| (Timestamp::MIN_NANO..=Timestamp::MAX_NANO).contains(n))
+            .ok_or_else(|| format!("'{literal}"))?;
         Ok(Timestamp { nanoseconds })
     }
 
+    pub fn is_null(&self) -> bool {
+        self.nanoseconds == i64::MIN
+    }
+
+    pub fn is_inf(&self) -> bool {
+        self.nanoseconds == i64::MAX || self.nanoseconds == -i64::MAX
+    }
+
     pub fn to_literal(self) -> String {
+        if self.is_null() {
+            return "0Np".to_string();
+        }
+        if self.is_inf() {
+            let sign = if self.nanoseconds < 0 { "-" } else { "" };
+            return format!("{sign}0Wp");
+        }
         let dt = self.to_naive_date_time();
         format!(
             "{:04}.{:02}.{:02}D{:02}:{:02}:{:02}.{:09}",
@@ -225,6 +323,24 @@ impl Timestamp {
         Timestamp { nanoseconds }
     }
 
+    /// q's `date + time`: the timestamp at the time of day `tod` on `date`. A null date or
+    /// time gives null, and a result outside the timestamp range is an error.
+    pub fn from_date_and_time(date: Date, tod: Timespan) -> Result<Self, String> {
+        if date.is_null() || tod.is_null() {
+            return Ok(Timestamp::NULL);
+        }
+        let error = || format!("'{}+{}", date.to_literal(), tod.to_literal());
+        if date.is_inf() || tod.is_inf() {
+            return Err(error());
+        }
+        let nanoseconds = (date.days as i64)
+            .checked_mul(86_400_000_000_000)
+            .and_then(|n| n.checked_add(tod.nanoseconds))
+            .filter(|n| (Timestamp::MIN_NANO..=Timestamp::MAX_NANO).contains(n))
+            .ok_or_else(error)?;
+        Ok(Timestamp { nanoseconds })
+    }
+
     pub fn year(&self) -> i32 {
         self.to_naive_date_time().year()
     }
@@ -263,7 +379,7 @@ impl Timestamp {
 
 impl From<i64> for Timestamp {
     fn from(nanoseconds: i64) -> Self {
-        assert!((Timestamp::MIN_NANO..Timestamp::MAX_NANO).contains(&nanoseconds));
+        assert!((Timestamp::MIN_NANO..=Timestamp::MAX_NANO).contains(&nanoseconds));
         Timestamp { nanoseconds }
     }
 }
@@ -302,6 +418,10 @@ impl Add<i64> for Timestamp {
     type Output = Timestamp;
 
     fn add(self, rhs: i64) -> Timestamp {
+        // nulls and infinities absorb the offset
+        if self.is_null() || self.is_inf() {
+            return self;
+        }
         Timestamp {
             nanoseconds: self.to_i64() + rhs,
         }
@@ -312,6 +432,9 @@ impl Add<Timestamp> for i64 {
     type Output = Timestamp;
 
     fn add(self, rhs: Timestamp) -> Timestamp {
+        if rhs.is_null() || rhs.is_inf() {
+            return rhs;
+        }
         Timestamp {
             nanoseconds: self + rhs.to_i64(),
         }
@@ -322,6 +445,10 @@ impl Sub<i64> for Timestamp {
     type Output = Timestamp;
 
     fn sub(self, rhs: i64) -> Timestamp {
+        // nulls and infinities absorb the offset
+        if self.is_null() || self.is_inf() {
+            return self;
+        }
         Timestamp {
             nanoseconds: self.to_i64() - rhs,
         }
@@ -332,6 +459,14 @@ impl Sub<Timestamp> for i64 {
     type Output = Timestamp;
 
     fn sub(self, rhs: Timestamp) -> Timestamp {
+        if rhs.is_null() {
+            return rhs;
+        }
+        if rhs.is_inf() {
+            return Timestamp {
+                nanoseconds: -rhs.nanoseconds,
+            };
+        }
         Timestamp {
             nanoseconds: self - rhs.to_i64(),
         }
@@ -358,9 +493,18 @@ impl Month {
     pub const MIN: Month = Month {
         months: Month::MIN_MONTHS,
     }; // 0001.01
+    pub const NULL: Month = Month { months: i32::MIN }; // 0Nm
+    pub const INF: Month = Month { months: i32::MAX }; // 0Wm
+    pub const NEG_INF: Month = Month { months: -i32::MAX }; // -0Wm
 
     /// Creates a Month from a literal string in format "YYYY.MMm"
     pub fn from_literal(literal: &str) -> Result<Self, String> {
+        match literal {
+            "0Nm" => return Ok(Month::NULL),
+            "0Wm" => return Ok(Month::INF),
+            "-0Wm" => return Ok(Month::NEG_INF),
+            _ => {}
+        }
         // Expected format: "YYYY.MMm" (exactly 8 characters)
         if literal.len() != 8 || !literal.ends_with('m') || literal.as_bytes()[4] != b'.' {
             return Err(format!("'{literal}"));
@@ -379,8 +523,23 @@ impl Month {
         Ok(Month { months })
     }
 
+    pub fn is_null(&self) -> bool {
+        self.months == i32::MIN
+    }
+
+    pub fn is_inf(&self) -> bool {
+        self.months == i32::MAX || self.months == -i32::MAX
+    }
+
     /// Converts the Month to a literal string in format "YYYY.MMm"
     pub fn to_literal(self) -> String {
+        if self.is_null() {
+            return "0Nm".to_string();
+        }
+        if self.is_inf() {
+            let sign = if self.months < 0 { "-" } else { "" };
+            return format!("{sign}0Wm");
+        }
         let total_months = self.months + (2000 * 12); // months since year 0
         let year = total_months / 12;
         let month = (total_months % 12) + 1;
@@ -405,6 +564,19 @@ impl Month {
     pub fn to_i32(self) -> i32 {
         self.months
     }
+
+    /// Each month from `self` up to and including `end`, which is empty if `end` is earlier
+    /// or either is null or infinite
+    pub fn iter_to(self, end: Month) -> impl Iterator<Item = Month> {
+        let special = |x: Month| x.is_null() || x.is_inf();
+        // an empty range, as from 1 to 0, for special endpoints
+        let (first, last) = if special(self) || special(end) {
+            (1, 0)
+        } else {
+            (self.months, end.months)
+        };
+        (first..=last).map(Month::from_i32)
+    }
 }
 
 impl From<i32> for Month {
@@ -454,6 +626,10 @@ impl Add<i32> for Month {
     type Output = Month;
 
     fn add(self, rhs: i32) -> Month {
+        // nulls and infinities absorb the offset
+        if self.is_null() || self.is_inf() {
+            return self;
+        }
         Month {
             months: self.to_i32() + rhs,
         }
@@ -464,6 +640,9 @@ impl Add<Month> for i32 {
     type Output = Month;
 
     fn add(self, rhs: Month) -> Month {
+        if rhs.is_null() || rhs.is_inf() {
+            return rhs;
+        }
         Month {
             months: self + rhs.to_i32(),
         }
@@ -474,6 +653,10 @@ impl Sub<i32> for Month {
     type Output = Month;
 
     fn sub(self, rhs: i32) -> Month {
+        // nulls and infinities absorb the offset
+        if self.is_null() || self.is_inf() {
+            return self;
+        }
         Month {
             months: self.to_i32() - rhs,
         }
@@ -484,6 +667,14 @@ impl Sub<Month> for i32 {
     type Output = Month;
 
     fn sub(self, rhs: Month) -> Month {
+        if rhs.is_null() {
+            return rhs;
+        }
+        if rhs.is_inf() {
+            return Month {
+                months: -rhs.months,
+            };
+        }
         Month {
             months: self - rhs.to_i32(),
         }
@@ -495,8 +686,9 @@ pub struct Timespan {
     nanoseconds: i64,
 }
 
-static TIMESPAN_RE: LazyLock<Regex> =
-    LazyLock::new(|| Regex::new(r"^(-?\d+)D(\d{2}):(\d{2}):(\d{2})(?:\.(\d{1,9}))?$").unwrap());
+static TIMESPAN_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(-?)(\d+)D(?:(\d{2})(?::(\d{2})(?::(\d{2})(?:\.(\d{1,9}))?)?)?)?$").unwrap()
+});
 
 impl Timespan {
     const MIN_NANO: i64 = -i64::MAX + 1;
@@ -504,40 +696,88 @@ impl Timespan {
     pub const MIN: Timespan = Timespan {
         nanoseconds: Timespan::MIN_NANO,
     };
+    pub const NULL: Timespan = Timespan {
+        nanoseconds: i64::MIN,
+    }; // 0Nn
+    pub const INF: Timespan = Timespan {
+        nanoseconds: i64::MAX,
+    }; // 0Wn
+    pub const NEG_INF: Timespan = Timespan {
+        nanoseconds: -i64::MAX,
+    }; // -0Wn
     pub const MAX: Timespan = Timespan {
         nanoseconds: Timespan::MAX_NANO,
     };
 
     pub fn from_literal(literal: &str) -> Result<Self, String> {
+        match literal {
+            "0Nn" => return Ok(Timespan::NULL),
+            "0Wn" => return Ok(Timespan::INF),
+            "-0Wn" => return Ok(Timespan::NEG_INF),
+            _ => {}
+        }
         let caps = TIMESPAN_RE
             .captures(literal)
             .ok_or_else(|| format!("'{literal}"))?;
 
-        let days: i64 = caps[1].parse().map_err(|_| format!("'{literal}"))?;
-        let hours: i64 = caps[2].parse().map_err(|_| format!("'{literal}"))?;
-        let minutes: i64 = caps[3].parse().map_err(|_| format!("'{literal}"))?;
-        let seconds: i64 = caps[4].parse().map_err(|_| format!("'{literal}"))?;
-
+        // the sign negates the whole span, and missing fields of the time are zero, e.g. `1D`
+        let field = |i: usize| caps.get(i).map_or(Ok(0), |m| m.as_str().parse::<i64>());
+        let days = field(2).map_err(|_| format!("'{literal}"))?;
+        let (hours, minutes, seconds) = (field(3).unwrap(), field(4).unwrap(), field(5).unwrap());
         let nanos: i64 = caps
-            .get(5)
-            .map(|m| {
-                let nanos_str = format!("{:0<9}", m.as_str());
-                nanos_str[0..9].parse().unwrap_or(0)
-            })
+            .get(6)
+            .map(|m| format!("{:0<9}", m.as_str()).parse().unwrap())
             .unwrap_or(0);
 
-        let nanoseconds = days * 86400 * 1_000_000_000
-            + hours * 3600 * 1_000_000_000
-            + minutes * 60 * 1_000_000_000
-            + seconds * 1_000_000_000
-            + nanos;
-
-        assert!((Timespan::MIN_NANO..=Timespan::MAX_NANO).contains(&nanoseconds));
+        let magnitude = days
+            .checked_mul(86400)
+            .and_then(|s| s.checked_add(hours * 3600 + minutes * 60 + seconds))
+            .and_then(|s| s.checked_mul(1_000_000_000))
+            .and_then(|n| n.checked_add(nanos))
+            .filter(|n| *n <= Timespan::MAX_NANO)
+            .ok_or_else(|| format!("'{literal}"))?;
+        let nanoseconds = if &caps[1] == "-" {
+            -magnitude
+        } else {
+            magnitude
+        };
         Ok(Timespan { nanoseconds })
     }
 
+    /// Converts a `std::time::Duration`, which must not exceed `Timespan::MAX`
+    pub fn from_std_duration(d: std::time::Duration) -> Result<Self, String> {
+        i64::try_from(d.as_nanos())
+            .ok()
+            .filter(|n| *n <= Timespan::MAX_NANO)
+            .map(|nanoseconds| Timespan { nanoseconds })
+            .ok_or_else(|| format!("{d:?} is beyond the range of a timespan"))
+    }
+
+    /// Converts to a `std::time::Duration`, which cannot be negative, null or infinite
+    pub fn to_std_duration(&self) -> Result<std::time::Duration, String> {
+        if self.is_null() || self.is_inf() || self.nanoseconds < 0 {
+            return Err(format!("{} is not a valid duration", self.to_literal()));
+        }
+        Ok(std::time::Duration::from_nanos(self.nanoseconds as u64))
+    }
+
+    pub fn is_null(&self) -> bool {
+        self.nanoseconds == i64::MIN
+    }
+
+    pub fn is_inf(&self) -> bool {
+        self.nanoseconds == i64::MAX || self.nanoseconds == -i64::MAX
+    }
+
     /// Converts the Timespan to a literal string in format "DDxDHH:MM:SS.nnnnnnnnn"
     pub fn to_literal(self) -> String {
+        if self.is_null() {
+            return "0Nn".to_string();
+        }
+        if self.is_inf() {
+            let sign = if self.nanoseconds < 0 { "-" } else { "" };
+            return format!("{sign}0Wn");
+        }
         let is_negative = self.nanoseconds < 0;
         let abs_nanos = self.nanoseconds.abs();
 
@@ -632,6 +872,10 @@ impl Add<i64> for Timespan {
     type Output = Timespan;
 
     fn add(self, rhs: i64) -> Timespan {
+        // nulls and infinities absorb the offset
+        if self.is_null() || self.is_inf() {
+            return self;
+        }
         Timespan {
             nanoseconds: self.to_i64() + rhs,
         }
@@ -642,6 +886,9 @@ impl Add<Timespan> for i64 {
     type Output = Timespan;
 
     fn add(self, rhs: Timespan) -> Timespan {
+        if rhs.is_null() || rhs.is_inf() {
+            return rhs;
+        }
         Timespan {
             nanoseconds: self + rhs.to_i64(),
         }
@@ -652,6 +899,10 @@ impl Sub<i64> for Timespan {
     type Output = Timespan;
 
     fn sub(self, rhs: i64) -> Timespan {
+        // nulls and infinities absorb the offset
+        if self.is_null() || self.is_inf() {
+            return self;
+        }
         Timespan {
             nanoseconds: self.to_i64() - rhs,
         }
@@ -662,6 +913,14 @@ impl Sub<Timespan> for i64 {
     type Output = Timespan;
 
     fn sub(self, rhs: Timespan) -> Timespan {
+        if rhs.is_null() {
+            return rhs;
+        }
+        if rhs.is_inf() {
+            return Timespan {
+                nanoseconds: -rhs.nanoseconds,
+            };
+        }
         Timespan {
             nanoseconds: self - rhs.to_i64(),
         }
@@ -702,8 +961,17 @@ impl Minute {
     pub const MIN: Minute = Minute {
         minutes: Minute::MIN_MINUTES,
     };
+    pub const NULL: Minute = Minute { minutes: i32::MIN }; // 0Nu
+    pub const INF: Minute = Minute { minutes: i32::MAX }; // 0Wu
+    pub const NEG_INF: Minute = Minute { minutes: -i32::MAX }; // -0Wu
 
     pub fn from_literal(literal: &str) -> Result<Self, String> {
+        match literal {
+            "0Nu" => return Ok(Minute::NULL),
+            "0Wu" => return Ok(Minute::INF),
+            "-0Wu" => return Ok(Minute::NEG_INF),
+            _ => {}
+        }
         if literal.len() != 5 || literal.as_bytes()[2] != b':' {
             return Err(format!("'{literal}"));
         }
@@ -720,7 +988,22 @@ impl Minute {
         Ok(Minute { minutes })
     }
 
+    pub fn is_null(&self) -> bool {
+        self.minutes == i32::MIN
+    }
+
+    pub fn is_inf(&self) -> bool {
+        self.minutes == i32::MAX || self.minutes == -i32::MAX
+    }
+
     pub fn to_literal(self) -> String {
+        if self.is_null() {
+            return "0Nu".to_string();
+        }
+        if self.is_inf() {
+            let sign = if self.minutes < 0 { "-" } else { "" };
+            return format!("{sign}0Wu");
+        }
         // let total_mins = self.minutes.rem_eucuid(1440);
         let hours = self.minutes / 60;
         let mins = self.minutes % 60;
@@ -784,6 +1067,10 @@ impl Add<i32> for Minute {
     type Output = Minute;
 
     fn add(self, rhs: i32) -> Minute {
+        // nulls and infinities absorb the offset
+        if self.is_null() || self.is_inf() {
+            return self;
+        }
         Minute {
             minutes: self.to_i32() + rhs,
         }
@@ -794,6 +1081,9 @@ impl Add<Minute> for i32 {
     type Output = Minute;
 
     fn add(self, rhs: Minute) -> Minute {
+        if rhs.is_null() || rhs.is_inf() {
+            return rhs;
+        }
         Minute {
             minutes: self + rhs.to_i32(),
         }
@@ -804,6 +1094,10 @@ impl Sub<i32> for Minute {
     type Output = Minute;
 
     fn sub(self, rhs: i32) -> Minute {
+        // nulls and infinities absorb the offset
+        if self.is_null() || self.is_inf() {
+            return self;
+        }
         Minute {
             minutes: self.to_i32() - rhs,
         }
@@ -814,6 +1108,14 @@ impl Sub<Minute> for i32 {
     type Output = Minute;
 
     fn sub(self, rhs: Minute) -> Minute {
+        if rhs.is_null() {
+            return rhs;
+        }
+        if rhs.is_inf() {
+            return Minute {
+                minutes: -rhs.minutes,
+            };
+        }
         Minute {
             minutes: self - rhs.to_i32(),
         }
@@ -834,8 +1136,17 @@ impl Second {
     pub const MIN: Second = Second {
         seconds: Second::MIN_SECONDS,
     };
+    pub const NULL: Second = Second { seconds: i32::MIN }; // 0Nv
+    pub const INF: Second = Second { seconds: i32::MAX }; // 0Wv
+    pub const NEG_INF: Second = Second { seconds: -i32::MAX }; // -0Wv
 
     pub fn from_literal(literal: &str) -> Result<Self, String> {
+        match literal {
+            "0Nv" => return Ok(Second::NULL),
+            "0Wv" => return Ok(Second::INF),
+            "-0Wv" => return Ok(Second::NEG_INF),
+            _ => {}
+        }
         if literal.len() != 8 || literal.as_bytes()[2] != b':' || literal.as_bytes()[5] != b':' {
             return Err(format!("'{literal}"));
         }
@@ -853,7 +1164,22 @@ impl Second {
         Ok(Second { seconds })
     }
 
+    pub fn is_null(&self) -> bool {
+        self.seconds == i32::MIN
+    }
+
+    pub fn is_inf(&self) -> bool {
+        self.seconds == i32::MAX || self.seconds == -i32::MAX
+    }
+
     pub fn to_literal(self) -> String {
+        if self.is_null() {
+            return "0Nv".to_string();
+        }
+        if self.is_inf() {
+            let sign = if self.seconds < 0 { "-" } else { "" };
+            return format!("{sign}0Wv");
+        }
         let total_secs = self.seconds.rem_euclid(86400);
         let hours = total_secs / 3600;
         let mins = (total_secs % 3600) / 60;
@@ -918,6 +1244,10 @@ impl Add<i32> for Second {
     type Output = Second;
 
     fn add(self, rhs: i32) -> Second {
+        // nulls and infinities absorb the offset
+        if self.is_null() || self.is_inf() {
+            return self;
+        }
         Second {
             seconds: self.to_i32() + rhs,
         }
@@ -928,6 +1258,9 @@ impl Add<Second> for i32 {
     type Output = Second;
 
     fn add(self, rhs: Second) -> Second {
+        if rhs.is_null() || rhs.is_inf() {
+            return rhs;
+        }
         Second {
             seconds: self + rhs.to_i32(),
         }
@@ -938,6 +1271,10 @@ impl Sub<i32> for Second {
     type Output = Second;
 
     fn sub(self, rhs: i32) -> Second {
+        // nulls and infinities absorb the offset
+        if self.is_null() || self.is_inf() {
+            return self;
+        }
         Second {
             seconds: self.to_i32() - rhs,
         }
@@ -948,6 +1285,14 @@ impl Sub<Second> for i32 {
     type Output = Second;
 
     fn sub(self, rhs: Second) -> Second {
+        if rhs.is_null() {
+            return rhs;
+        }
+        if rhs.is_inf() {
+            return Second {
+                seconds: -rhs.seconds,
+            };
+        }
         Second {
             seconds: self - rhs.to_i32(),
         }
@@ -1153,3 +1498,269 @@ impl Sub<Timespan> for Second {
         }
     }
 }
+
+// Cross-type operations between Timestamp and Minute/Second/Timespan
+
+impl Timestamp {
+    /// Advances by `nanoseconds`, where a null on either side gives null, an infinite
+    /// timestamp absorbs the offset, and an infinite offset (`i64::MAX` or `-i64::MAX`) or one
+    /// that moves out of range gives the infinity in its direction
+    fn offset(self, nanoseconds: Option<i64>) -> Timestamp {
+        let Some(nanoseconds) = nanoseconds.filter(|_| !self.is_null()) else {
+            return Timestamp::NULL;
+        };
+        if self.is_inf() {
+            return self;
+        }
+        let toward = if nanoseconds > 0 {
+            Timestamp::INF
+        } else {
+            Timestamp::NEG_INF
+        };
+        match self.nanoseconds.checked_add(nanoseconds) {
+            _ if nanoseconds.abs() == i64::MAX => toward,
+            Some(n) if (Timestamp::MIN_NANO..=Timestamp::MAX_NANO).contains(&n) => {
+                Timestamp { nanoseconds: n }
+            }
+            _ => toward,
+        }
+    }
+}
+
+/// The nanoseconds in `raw` units of `unit` nanoseconds, `None` for a null, and `i64::MAX`
+/// or `-i64::MAX` for an infinity or a span too long to count in nanoseconds
+fn span_nanos(raw: i64, null: bool, inf: bool, unit: i64) -> Option<i64> {
+    let saturated = raw.signum() * i64::MAX;
+    match raw.checked_mul(unit) {
+        _ if null => None,
+        _ if inf => Some(saturated),
+        Some(n) => Some(n),
+        None => Some(saturated),
+    }
+}
+
+// the underlying ints of the int-based temporal types, with range checking instead of asserts
+macro_rules! impl_from_raw {
+    ($($t:ident.$field:ident in $min:expr, $max:expr);*) => {
+        $(
+            impl $t {
+                /// The value whose underlying int is `raw`, which may be the null or an
+                /// infinity, or `None` when `raw` is out of range
+                pub(crate) fn from_raw(raw: i32) -> Option<Self> {
+                    let special = raw == i32::MIN || raw == i32::MAX || raw == -i32::MAX;
+                    (special || ($min..=$max).contains(&raw)).then_some($t { $field: raw })
+                }
+            }
+        )*
+    };
+}
+
+impl_from_raw!(
+    Date.days in Date::MIN_DAYS, Date::MAX_DAYS;
+    Month.months in Month::MIN_MONTHS, Month::MAX_MONTHS;
+    Minute.minutes in Minute::MIN_MINUTES, Minute::MAX_MINUTES;
+    Second.seconds in Second::MIN_SECONDS, Second::MAX_SECONDS
+);
+
+// maps the underlying int of a temporal value, for shifts in bulk
+macro_rules! impl_map_raw {
+    ($($t:ident.$field:ident: $int:ident in $min:expr, $max:expr);*) => {
+        $(
+            impl $t {
+                /// The value whose underlying int is `f` of this one's, clamped into range.
+                /// Nulls and infinities are kept as they are.
+                pub(crate) fn map_raw(self, f: impl Fn(i64) -> i64) -> Self {
+                    if self.is_null() || self.is_inf() {
+                        return self;
+                    }
+                    let raw = f(self.$field as i64).clamp($min as i64, $max as i64);
+                    $t { $field: raw as $int }
+                }
+            }
+        )*
+    };
+}
+
+impl_map_raw!(
+    Timestamp.nanoseconds: i64 in Timestamp::MIN_NANO, Timestamp::MAX_NANO;
+    Month.months: i32 in Month::MIN_MONTHS, Month::MAX_MONTHS;
+    Date.days: i32 in Date::MIN_DAYS, Date::MAX_DAYS;
+    Timespan.nanoseconds: i64 in Timespan::MIN_NANO, Timespan::MAX_NANO;
+    Minute.minutes: i32 in Minute::MIN_MINUTES, Minute::MAX_MINUTES;
+    Second.seconds: i32 in Second::MIN_SECONDS, Second::MAX_SECONDS
+);
+
+macro_rules! impl_timestamp_offset {
+    ($($t:ty => $nanoseconds:expr),*) => {
+        $(
+            impl Add<$t> for Timestamp {
+                type Output = Timestamp;
+
+                fn add(self, rhs: $t) -> Timestamp {
+                    self.offset($nanoseconds(rhs))
+                }
+            }
+
+            impl Add<Timestamp> for $t {
+                type Output = Timestamp;
+
+                fn add(self, rhs: Timestamp) -> Timestamp {
+                    rhs.offset($nanoseconds(self))
+                }
+            }
+
+            impl Sub<$t> for Timestamp {
+                type Output = Timestamp;
+
+                fn sub(self, rhs: $t) -> Timestamp {
+                    self.offset($nanoseconds(rhs).map(|n: i64| -n))
+                }
+            }
+        )*
+    };
+}
+
+impl_timestamp_offset!(
+    Minute => |m: Minute| span_nanos(m.minutes as i64, m.is_null(), m.is_inf(), 60_000_000_000),
+    Second => |s: Second| span_nanos(s.seconds as i64, s.is_null(), s.is_inf(), 1_000_000_000),
+    Timespan => |t: Timespan| span_nanos(t.nanoseconds, t.is_null(), t.is_inf(), 1)
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::qtype::Q;
+
+    fn q(input: &str) -> Q {
+        crate::parse_one(input).unwrap()
+    }
+
+    #[test]
+    fn infinities_parse_and_print() {
+        assert_eq!(q("0Wd"), Q::Date(Date::INF));
+        assert_eq!(q("0Wp"), Q::Timestamp(Timestamp::INF));
+        assert_eq!(q("0Wm"), Q::Month(Month::INF));
+        assert_eq!(q("0Wn"), Q::Timespan(Timespan::INF));
+        assert_eq!(q("0Wu"), Q::Minute(Minute::INF));
+        assert_eq!(q("0Wv"), Q::Second(Second::INF));
+        assert_eq!(q("-0Wd"), Q::Date(Date::NEG_INF));
+        for literal in ["0Wd", "0Wp", "0Wm", "0Wn", "0Wu", "0Wv", "-0Wd", "-0Wp"] {
+            assert_eq!(q(literal).to_string(), literal);
+        }
+    }
+
+    #[test]
+    fn infinities_saturate() {
+        assert_eq!(Date::INF + 1, Date::INF);
+        assert_eq!(Date::NEG_INF - 1, Date::NEG_INF);
+        assert_eq!(Month::INF + 1, Month::INF);
+        assert_eq!(Timestamp::INF + 1, Timestamp::INF);
+        assert_eq!(1 - Timestamp::INF, Timestamp::NEG_INF);
+    }
+
+    #[test]
+    fn dates_and_months_iterate_inclusively() {
+        let week: Q = Date::from_literal("2024.01.01")
+            .unwrap()
+            .iter_to(Date::from_literal("2024.01.07").unwrap())
+            .collect();
+        assert_eq!(
+            week,
+            q("2024.01.01 2024.01.02 2024.01.03 2024.01.04 2024.01.05 2024.01.06 2024.01.07")
+        );
+        let months: Q = Month::from_literal("2023.11m")
+            .unwrap()
+            .iter_to(Month::from_literal("2024.01m").unwrap())
+            .collect();
+        assert_eq!(months, q("2023.11 2023.12 2024.01m"));
+        let start = Date::from_literal("2024.01.02").unwrap();
+        assert_eq!(
+            start
+                .iter_to(Date::from_literal("2024.01.01").unwrap())
+                .count(),
+            0
+        );
+        assert_eq!(start.iter_to(Date::INF).count(), 0);
+        assert_eq!(Date::NULL.iter_to(start).count(), 0);
+    }
+
+    #[test]
+    fn flexible_dates_accept_any_one_separator() {
+        let date = Date::from_literal("2001.05.17").unwrap();
+        for literal in ["2001.05.17", "2001/05/17", "2001-05-17"] {
+            assert_eq!(Date::from_literal_flexible(literal), Ok(date));
+        }
+        assert!(Date::from_literal_flexible("2001-05/17").is_err());
+        assert!(Date::from_literal("2001-05-17").is_err());
+    }
+
+    #[test]
+    fn timestamps_advance_by_minutes_seconds_and_timespans() {
+        let ts = |literal| Timestamp::from_literal(literal).unwrap();
+        let start = ts("2000.01.01D12:00:00.000000000");
+        let minutes = Minute::from_literal("01:30").unwrap();
+        let seconds = Second::from_literal("00:00:45").unwrap();
+        assert_eq!(start + minutes, ts("2000.01.01D13:30:00.000000000"));
+        assert_eq!(seconds + start, ts("2000.01.01D12:00:45.000000000"));
+        assert_eq!(start - minutes, ts("2000.01.01D10:30:00.000000000"));
+        let span = Timespan::from_literal("1D00:00:00.000000001").unwrap();
+        assert_eq!(start + span, ts("2000.01.02D12:00:00.000000001"));
+    }
+
+    #[test]
+    fn timestamp_offsets_absorb_nulls_and_infinities() {
+        let start = Timestamp::from_literal("2000.01.01D12:00:00.000000000").unwrap();
+        assert_eq!(start + Minute::NULL, Timestamp::NULL);
+        assert_eq!(Timestamp::NULL + Second::INF, Timestamp::NULL);
+        assert_eq!(start + Minute::INF, Timestamp::INF);
+        assert_eq!(start - Minute::INF, Timestamp::NEG_INF);
+        assert_eq!(start + Timespan::NEG_INF, Timestamp::NEG_INF);
+        assert_eq!(Timestamp::INF - Second::INF, Timestamp::INF);
+        assert_eq!(Timestamp::MAX + Timespan::MAX, Timestamp::INF);
+    }
+
+    #[test]
+    fn timespans_convert_to_and_from_std_durations() {
+        let ninety = std::time::Duration::from_secs(90 * 60);
+        let span = Timespan::from_std_duration(ninety).unwrap();
+        assert_eq!(span, Timespan::from_literal("0D01:30:00").unwrap());
+        assert_eq!(span.to_std_duration(), Ok(ninety));
+        let negative = Timespan::from_literal("-0D00:00:01").unwrap();
+        assert!(negative.to_std_duration().is_err());
+        assert!(Timespan::NULL.to_std_duration().is_err());
+        assert!(Timespan::from_std_duration(std::time::Duration::MAX).is_err());
+    }
+
+    #[test]
+    fn timestamps_compose_from_a_date_and_a_time() {
+        let date = Date::from_literal("2001.01.01").unwrap();
+        let tod = Timespan::from_literal("0D09:30:00").unwrap();
+        assert_eq!(
+            Timestamp::from_date_and_time(date, tod),
+            Timestamp::from_literal("2001.01.01D09:30:00.000000000")
+        );
+        assert_eq!(
+            Timestamp::from_date_and_time(Date::NULL, tod),
+            Ok(Timestamp::NULL)
+        );
+        assert!(Timestamp::from_date_and_time(Date::MAX, tod).is_err());
+        assert!(Timestamp::from_date_and_time(date, Timespan::INF).is_err());
+    }
+
+    #[test]
+    fn timestamp_literals_reach_both_ends_of_the_range() {
+        let (min, max) = (
+            "1707.09.22D00:12:43.145224194",
+            "2292.04.10D23:47:16.854775806",
+        );
+        assert_eq!(Timestamp::from_literal(min), Ok(Timestamp::MIN));
+        assert_eq!(Timestamp::from_literal(max), Ok(Timestamp::MAX));
+        assert_eq!(q(min), Q::Timestamp(Timestamp::MIN));
+        assert_eq!(q(max), Q::Timestamp(Timestamp::MAX));
+        assert_eq!(Timestamp::MAX.to_literal(), max);
+        assert_eq!(Timestamp::from(Timestamp::MAX.to_i64()), Timestamp::MAX);
+        assert_eq!(Timestamp::from(Timestamp::MIN.to_i64()), Timestamp::MIN);
+        assert!(Timestamp::from_literal("2292.04.10D23:47:16.854775807").is_err());
+        assert!(Timestamp::from_literal("1707.09.22D00:12:43.145224193").is_err());
+    }
+}
@@ -0,0 +1,116 @@
+// Rendering tables as CSV text, as q's `csv 0: t`
+use crate::qtype::{Q, QError};
+
+/// How dates, months and timestamps are written in CSV cells
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum DateFmt {
+    /// q's own literals, e.g. `2024.01.31` and `2024.01.31D09:30:00.000000000`
+    #[default]
+    Q,
+    /// ISO 8601, e.g. `2024-01-31` and `2024-01-31T09:30:00.000000000`
+    Iso,
+}
+
+/// Options for `Q::to_csv`. The default writes nulls as empty cells and dates as q does.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CsvOptions {
+    /// The text of a null cell, e.g. `NA`
+    pub null_repr: String,
+    pub date_fmt: DateFmt,
+}
+
+impl Q {
+    /// A table, or a keyed table with its key columns first, as CSV: a header line of column
+    /// names, then one line per row
+    pub fn to_csv(&self, options: &CsvOptions) -> Result<String, QError> {
+        let (columns, values) = match self {
+            Q::Table(columns, values) => (columns.clone(), values.clone()),
+            Q::Dict(keys, values) => match (keys.as_ref(), values.as_ref()) {
+                (Q::Table(k, kv), Q::Table(v, vv)) => (
+                    k.iter().chain(v).copied().collect(),
+                    kv.iter().chain(vv).cloned().collect(),
+                ),
+                _ => return Err(QError::Type),
+            },
+            _ => return Err(QError::Type),
+        };
+        let header: Vec<String> = columns.iter().map(|c| quote(c.resolve())).collect();
+        let mut lines = vec![header.join(",")];
+        for i in 0..self.len() {
+            let row = values
+                .iter()
+                .map(|v| v.at(i).ok_or(QError::Length)?.format_csv_cell(options))
+                .collect::<Result<Vec<_>, _>>()?;
+            lines.push(row.join(","));
+        }
+        Ok(lines.join("\n"))
+    }
+
+    /// An atom or string as one CSV cell, quoted when it holds a comma, quote or line break
+    pub fn format_csv_cell(&self, options: &CsvOptions) -> Result<String, QError> {
+        let iso = options.date_fmt == DateFmt::Iso;
+        let text = match self {
+            Q::Char(c) => String::from_utf8_lossy(&[*c]).into_owned(),
+            Q::String(s) => String::from_utf8_lossy(s).into_owned(),
+            atom if atom.is_atom() && atom.null_count() == 1 => options.null_repr.clone(),
+            Q::Date(d) if iso && !d.is_inf() => d.to_literal().replace('.', "-"),
+            Q::Month(m) if iso && !m.is_inf() => {
+                m.to_literal().trim_end_matches('m').replace('.', "-")
+            }
+            Q::Timestamp(t) if iso && !t.is_inf() => {
+                t.to_literal().replacen('.', "-", 2).replace('D', "T")
+            }
+            Q::Enum { .. } => return Err(QError::Type),
+            atom if atom.is_atom() => atom.cell(),
+            _ => return Err(QError::Type),
+        };
+        Ok(quote(&text))
+    }
+}
+
+fn quote(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn q(input: &str) -> Q {
+        crate::parse_one(input).unwrap()
+    }
+
+    #[test]
+    fn tables_export_with_default_and_iso_dates() {
+        let t = q(
+            "([] d:2024.01.31 0Nd; m:2024.01 2024.02m; p:2024.01.31D09:30 0Np; n:1 0N; s:(\"x\";\"y,z\"))",
+        );
+        assert_eq!(
+            t.to_csv(&CsvOptions::default()),
+            Ok([
+                "d,m,p,n,s",
+                "2024.01.31,2024.01,2024.01.31D09:30:00.000000000,1,x",
+                ",2024.02,,,\"y,z\"",
+            ]
+            .join("\n"))
+        );
+        let iso = CsvOptions {
+            null_repr: "NA".to_string(),
+            date_fmt: DateFmt::Iso,
+        };
+        assert_eq!(
+            t.to_csv(&iso),
+            Ok([
+                "d,m,p,n,s",
+                "2024-01-31,2024-01,2024-01-31T09:30:00.000000000,1,x",
+                "NA,2024-02,NA,NA,\"y,z\"",
+            ]
+            .join("\n"))
+        );
+        assert_eq!(q("1 2").to_csv(&iso), Err(QError::Type));
+    }
+}
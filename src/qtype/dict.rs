@@ -0,0 +1,67 @@
+// Dictionaries, i.e. q's `keys!values`
+use crate::qtype::{Q, QError};
+
+impl Q {
+    /// q's `keys!values`; both sides must have the same count
+    pub fn dict(keys: Q, values: Q) -> Result<Q, QError> {
+        if keys.is_atom() || values.is_atom() || keys.len() != values.len() {
+            return Err(QError::Length);
+        }
+        Ok(Q::Dict(Box::new(keys), Box::new(values)))
+    }
+
+    /// q's `key` on a dictionary
+    pub fn keys(&self) -> Option<Q> {
+        match self {
+            Q::Dict(keys, _) => Some(keys.as_ref().clone()),
+            _ => None,
+        }
+    }
+
+    /// q's `value` on a dictionary
+    pub fn values(&self) -> Option<Q> {
+        match self {
+            Q::Dict(_, values) => Some(values.as_ref().clone()),
+            _ => None,
+        }
+    }
+
+    /// q's `d[k]`: the value of the first matching key, or the typed null of the values when
+    /// `key` is missing. `None` if `self` is not a dictionary.
+    pub fn dict_lookup(&self, key: &Q) -> Option<Q> {
+        let Q::Dict(keys, values) = self else {
+            return None;
+        };
+        let found = (0..keys.len()).find(|&i| keys.at(i).as_ref() == Some(key));
+        Some(
+            found
+                .and_then(|i| values.at(i))
+                .unwrap_or_else(|| values.null_item()),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::qtype::symbol::Symbol;
+
+    fn q(input: &str) -> Q {
+        crate::parse_one(input).unwrap()
+    }
+
+    #[test]
+    fn keys_values_and_lookup() {
+        let d = Q::dict(q("`a`b"), q("1 2")).unwrap();
+        assert_eq!(d.keys(), Some(q("`a`b")));
+        assert_eq!(d.values(), Some(q("1 2")));
+        assert_eq!(
+            d.dict_lookup(&Q::Symbol(Symbol::from("b"))),
+            Some(Q::Long(2))
+        );
+        assert_eq!(d.dict_lookup(&Q::Symbol(Symbol::from("c"))), Some(q("0N")));
+        assert_eq!(q("1 2").keys(), None);
+        assert_eq!(q("1 2").dict_lookup(&Q::Long(1)), None);
+        assert_eq!(Q::dict(q("`a`b"), q("1 2 3")), Err(QError::Length));
+    }
+}
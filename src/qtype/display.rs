@@ -0,0 +1,340 @@
+// q console rendering of `Q` values
+use crate::qtype::Q;
+use crate::qtype::null::Nullable;
+use crate::qtype::symbol::Symbol;
+use std::fmt;
+
+impl fmt::Display for Q {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Q::Dict(keys, values)
+                if matches!((&**keys, &**values), (Q::Table(..), Q::Table(..))) =>
+            {
+                write_keyed_table(f, keys, values)
+            }
+            Q::Dict(keys, values) => write_dict(f, keys, values),
+            Q::Table(columns, values) => {
+                let header = columns.iter().map(|c| c.resolve().to_string()).collect();
+                write_table(f, header, values)
+            }
+            _ => write!(f, "{}", self.to_q_string()),
+        }
+    }
+}
+
+impl Q {
+    /// Renders `self` on a single line, as q's `-3!`
+    pub fn to_q_string(&self) -> String {
+        match self {
+            Q::Boolean(_)
+            | Q::Guid(_)
+            | Q::Short(_)
+            | Q::Int(_)
+            | Q::Long(_)
+            | Q::Real(_)
+            | Q::Float(_)
+            | Q::Timestamp(_)
+            | Q::Month(_)
+            | Q::Date(_)
+            | Q::Timespan(_)
+            | Q::Minute(_)
+            | Q::Second(_) => format!("{}{}", self.cell(), suffix(self, &[self.cell()])),
+            Q::Byte(b) => format!("0x{b:02x}"),
+            Q::Char(c) => format!("\"{}\"", escape(&[*c])),
+            Q::Symbol(s) => s.to_string(),
+            Q::String(v) if v.len() == 1 => format!(",\"{}\"", escape(v)),
+            Q::String(v) => format!("\"{}\"", escape(v)),
+            Q::Symbols(v) if v.is_empty() => "`symbol$()".to_string(),
+            Q::Symbols(v) => {
+                let joined: String = v.iter().map(|s| s.to_string()).collect();
+                if v.len() == 1 {
+                    format!(",{joined}")
+                } else {
+                    joined
+                }
+            }
+            Q::Booleans(v) if !v.is_empty() => {
+                let bits: String = v.iter().map(|&b| if b { '1' } else { '0' }).collect();
+                let prefix = if v.len() == 1 { "," } else { "" };
+                format!("{prefix}{bits}b")
+            }
+            Q::Bytes(v) if !v.is_empty() => {
+                let hex: String = v.iter().map(|b| format!("{b:02x}")).collect();
+                let prefix = if v.len() == 1 { "," } else { "" };
+                format!("{prefix}0x{hex}")
+            }
+            Q::List(v) if v.len() == 1 => format!(",{}", v[0].to_q_string()),
+            Q::List(v) => {
+                let items: Vec<String> = v.iter().map(Q::to_q_string).collect();
+                format!("({})", items.join(";"))
+            }
+            Q::Dict(keys, values) => {
+                format!("{}!{}", keys.to_q_string(), values.to_q_string())
+            }
+            Q::Table(columns, values) => {
+                let dict = Q::Dict(
+                    Box::new(Q::Symbols(columns.clone())),
+                    Box::new(Q::List(values.clone())),
+                );
+                format!("+{}", dict.to_q_string())
+            }
+            Q::Enum { domain, indices } => format!("{domain}!{}", indices.to_q_string()),
+            Q::Null => "::".to_string(),
+            Q::Attributed(attr, list) => format!("`{}#{}", attr.code(), list.to_q_string()),
+            vector if vector.is_empty() => format!("`{}$()", type_name(vector)),
+            vector => {
+                let cells = vector.cells();
+                let prefix = if cells.len() == 1 { "," } else { "" };
+                format!("{prefix}{}{}", cells.join(" "), suffix(vector, &cells))
+            }
+        }
+    }
+
+    /// Renders an atom without type decoration, as it appears in a table cell
+    pub(crate) fn cell(&self) -> String {
+        match self {
+            Q::Boolean(b) => (if *b { "1" } else { "0" }).to_string(),
+            Q::Guid(g) => g.to_string(),
+            Q::Byte(b) => format!("{b:02x}"),
+            Q::Short(x) => integer(*x as i64, x.is_null(), *x == i16::MAX, *x == -i16::MAX),
+            Q::Int(x) => integer(*x as i64, x.is_null(), *x == i32::MAX, *x == -i32::MAX),
+            Q::Long(x) => integer(*x, x.is_null(), *x == i64::MAX, *x == -i64::MAX),
+            Q::Real(x) => float(*x as f64),
+            Q::Float(x) => float(*x),
+            Q::Char(c) => escape(&[*c]),
+            Q::Symbol(s) => s.resolve().to_string(),
+            Q::Timestamp(t) => t.to_literal(),
+            Q::Month(m) => m.to_literal().trim_end_matches('m').to_string(),
+            Q::Date(d) => d.to_literal(),
+            Q::Timespan(t) => t.to_literal(),
+            Q::Minute(m) => m.to_literal(),
+            Q::Second(s) => s.to_literal(),
+            Q::String(v) => escape(v),
+            other => other.to_q_string(),
+        }
+    }
+
+    fn cells(&self) -> Vec<String> {
+        match self.items() {
+            Ok(items) => items.iter().map(Q::cell).collect(),
+            // a dictionary or table in place of a list shows whole
+            Err(_) => vec![self.to_q_string()],
+        }
+    }
+}
+
+fn write_dict(f: &mut fmt::Formatter<'_>, keys: &Q, values: &Q) -> fmt::Result {
+    let keys = keys.cells();
+    let width = keys.iter().map(String::len).max().unwrap_or(0);
+    // a dictionary built by hand may have fewer values than keys, or an atom for its values
+    for (i, key) in keys.iter().enumerate() {
+        let value = values
+            .at(i)
+            .map_or_else(|| "::".to_string(), |v| v.to_q_string());
+        writeln!(f, "{key:<width$}| {value}")?;
+    }
+    Ok(())
+}
+
+/// Renders the columns under a header and a separator line, each padded to its widest cell.
+/// Numeric columns are right-aligned, all others left-aligned.
+fn write_table(f: &mut fmt::Formatter<'_>, header: Vec<String>, values: &[Q]) -> fmt::Result {
+    for line in table_lines(header, values) {
+        writeln!(f, "{line}")?;
+    }
+    Ok(())
+}
+
+/// Renders a keyed table, i.e. a dictionary from a table of keys to a table of values, with
+/// the key columns left of a `|`
+fn write_keyed_table(f: &mut fmt::Formatter<'_>, keys: &Q, values: &Q) -> fmt::Result {
+    let (Q::Table(kc, kv), Q::Table(vc, vv)) = (keys, values) else {
+        unreachable!()
+    };
+    let header = |columns: &[Symbol]| columns.iter().map(|c| c.resolve().to_string()).collect();
+    let keys = table_lines(header(kc), kv);
+    let values = table_lines(header(vc), vv);
+    let width = keys.iter().map(String::len).max().unwrap_or(0);
+    for (key, value) in keys.iter().zip(&values) {
+        writeln!(f, "{key:<width$}| {value}")?;
+    }
+    Ok(())
+}
+
+fn table_lines(header: Vec<String>, values: &[Q]) -> Vec<String> {
+    let columns: Vec<Vec<String>> = values.iter().map(Q::cells).collect();
+    let widths: Vec<usize> = header
+        .iter()
+        .zip(&columns)
+        .map(|(h, cells)| {
+            cells
+                .iter()
+                .map(String::len)
+                .chain([h.len()])
+                .max()
+                .unwrap()
+        })
+        .collect();
+    let numeric: Vec<bool> = values.iter().map(is_numeric).collect();
+
+    let line = |cells: Vec<&String>| -> String {
+        let padded: Vec<String> = cells
+            .iter()
+            .enumerate()
+            .map(|(j, cell)| {
+                let width = widths[j];
+                if numeric[j] {
+                    format!("{cell:>width$}")
+                } else {
+                    format!("{cell:<width$}")
+                }
+            })
+            .collect();
+        padded.join(" ").trim_end().to_string()
+    };
+
+    let mut lines = vec![line(header.iter().collect())];
+    let total = widths.iter().sum::<usize>() + widths.len().saturating_sub(1);
+    lines.push("-".repeat(total));
+    // a table built by hand may have ragged columns, whose missing cells are left blank
+    let blank = String::new();
+    let rows = columns.iter().map(Vec::len).max().unwrap_or(0);
+    for i in 0..rows {
+        lines.push(line(
+            columns.iter().map(|c| c.get(i).unwrap_or(&blank)).collect(),
+        ));
+    }
+    lines
+}
+
+fn is_numeric(q: &Q) -> bool {
+    matches!(
+        q,
+        Q::Bytes(_) | Q::Shorts(_) | Q::Ints(_) | Q::Longs(_) | Q::Reals(_) | Q::Floats(_)
+    )
+}
+
+/// Type suffix for a rendered atom or vector, given its rendered cells
+fn suffix(q: &Q, cells: &[String]) -> &'static str {
+    match q {
+        Q::Boolean(_) | Q::Booleans(_) => "b",
+        Q::Short(_) | Q::Shorts(_) => "h",
+        Q::Int(_) | Q::Ints(_) => "i",
+        Q::Real(_) | Q::Reals(_) => "e",
+        // floats which all look like integers need the suffix to read back as floats
+        Q::Float(_) | Q::Floats(_) if cells.iter().all(|c| looks_integral(c)) => "f",
+        Q::Month(_) | Q::Months(_) => "m",
+        _ => "",
+    }
+}
+
+fn looks_integral(cell: &str) -> bool {
+    cell.trim_start_matches('-')
+        .bytes()
+        .all(|b| b.is_ascii_digit())
+}
+
+// Type chars indexed by type id, as q's `.Q.t`
+pub(crate) const TYPE_CHARS: &str = " bg xhijefcspmdznuvt";
+
+pub(crate) fn type_name(q: &Q) -> &'static str {
+    match q {
+        Q::Booleans(_) => "boolean",
+        Q::Guids(_) => "guid",
+        Q::Bytes(_) => "byte",
+        Q::Shorts(_) => "short",
+        Q::Ints(_) => "int",
+        Q::Longs(_) => "long",
+        Q::Reals(_) => "real",
+        Q::Floats(_) => "float",
+        Q::String(_) => "char",
+        Q::Symbols(_) => "symbol",
+        Q::Timestamps(_) => "timestamp",
+        Q::Months(_) => "month",
+        Q::Dates(_) => "date",
+        Q::Timespans(_) => "timespan",
+        Q::Minutes(_) => "minute",
+        Q::Seconds(_) => "second",
+        _ => "",
+    }
+}
+
+fn integer(x: i64, null: bool, inf: bool, neg_inf: bool) -> String {
+    if null {
+        "0N".to_string()
+    } else if inf {
+        "0W".to_string()
+    } else if neg_inf {
+        "-0W".to_string()
+    } else {
+        x.to_string()
+    }
+}
+
+/// Formats a float with q's default display precision of 7 significant digits
+fn float(x: f64) -> String {
+    if x.is_nan() {
+        return "0n".to_string();
+    }
+    if x.is_infinite() {
+        return (if x > 0.0 { "0w" } else { "-0w" }).to_string();
+    }
+    if x == x.trunc() && x.abs() < 1e15 {
+        return format!("{}", x as i64);
+    }
+    let magnitude = x.abs().log10().floor() as i32 + 1;
+    if !(-4..=7).contains(&magnitude) {
+        return format!("{x:e}");
+    }
+    let decimals = (7 - magnitude).max(0) as usize;
+    let fixed = format!("{x:.decimals$}");
+    fixed
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .to_string()
+}
+
+fn escape(bytes: &[u8]) -> String {
+    let mut escaped = Vec::with_capacity(bytes.len());
+    for &b in bytes {
+        match b {
+            b'"' => escaped.extend_from_slice(b"\\\""),
+            b'\\' => escaped.extend_from_slice(b"\\\\"),
+            b'\n' => escaped.extend_from_slice(b"\\n"),
+            b'\t' => escaped.extend_from_slice(b"\\t"),
+            b'\r' => escaped.extend_from_slice(b"\\r"),
+            0x00..=0x1f | 0x7f => escaped.extend_from_slice(format!("\\{b:03o}").as_bytes()),
+            _ => escaped.push(b),
+        }
+    }
+    String::from_utf8_lossy(&escaped).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    fn q(input: &str) -> crate::Q {
+        crate::parse_one(input).unwrap()
+    }
+
+    #[test]
+    fn tables_show_in_aligned_columns() {
+        assert_eq!(
+            q("([] sym:`a`bb; px:1 20.5)").to_string(),
+            "sym   px\n--------\na      1\nbb  20.5\n"
+        );
+        assert_eq!(
+            q("([k:`a`bb] px:1 20.5)").to_string(),
+            "k |   px\n--| ----\na |    1\nbb| 20.5\n"
+        );
+    }
+
+    #[test]
+    fn ragged_tables_show_blank_cells() {
+        use crate::qtype::symbol::Symbol;
+        let ragged = crate::Q::Table(
+            vec![Symbol::from("a"), Symbol::from("b")],
+            vec![q("1 2"), q(",1")],
+        );
+        assert_eq!(ragged.to_string(), "a b\n---\n1 1\n2\n");
+    }
+}
@@ -0,0 +1,117 @@
+// Enumerated symbols, i.e. q's `` `domain$symbols ``, stored as indices into a named domain
+use crate::qtype::null::Nullable;
+use crate::qtype::symbol::Symbol;
+use crate::qtype::{Q, QError};
+use std::collections::HashMap;
+
+impl Q {
+    /// The symbols of an enumeration, looked up in `domain_values`, the symbol list bound to
+    /// its domain name. Null or out-of-range indices resolve to the null symbol. Anything
+    /// other than an enumeration resolves to itself.
+    pub fn resolve(&self, domain_values: &Q) -> Q {
+        let Q::Enum { indices, .. } = self else {
+            return self.clone();
+        };
+        let lookup = |i: i64| {
+            let found = usize::try_from(i).ok().and_then(|i| domain_values.at(i));
+            match found {
+                Some(Q::Symbol(s)) => s,
+                _ => Symbol::from(""),
+            }
+        };
+        match indices.as_ref() {
+            Q::Long(i) => Q::Symbol(lookup(*i)),
+            Q::Longs(v) => Q::Symbols(v.iter().map(|&i| lookup(i)).collect()),
+            _ => Q::Symbols(vec![]),
+        }
+    }
+
+    /// q's `` `name?values ``: enumerates the symbols `values` against `domain`, the symbol
+    /// list bound to `name`, appending any symbols it does not hold yet
+    pub fn enumerate(values: &Q, name: Symbol, domain: &mut Q) -> Result<Q, QError> {
+        let Q::Symbols(domain) = domain else {
+            return Err(QError::Type);
+        };
+        let mut positions: HashMap<Symbol, i64> = HashMap::with_capacity(domain.len());
+        for (i, d) in domain.iter().enumerate() {
+            positions.entry(*d).or_insert(i as i64);
+        }
+        let mut index = |s: &Symbol| {
+            *positions.entry(*s).or_insert_with(|| {
+                domain.push(*s);
+                domain.len() as i64 - 1
+            })
+        };
+        let indices = match values.without_attr_ref() {
+            Q::Symbol(s) => Q::Long(index(s)),
+            Q::Symbols(v) => Q::Longs(v.iter().map(index).collect()),
+            _ => return Err(QError::Type),
+        };
+        Ok(Q::Enum {
+            domain: name,
+            indices: Box::new(indices),
+        })
+    }
+
+    /// The enumeration item at `i`, as an enumerated atom
+    pub(crate) fn enum_at(domain: Symbol, indices: &Q, i: usize) -> Option<Q> {
+        indices.at(i).map(|index| Q::Enum {
+            domain,
+            indices: Box::new(index),
+        })
+    }
+
+    pub(crate) fn enum_null(domain: Symbol) -> Q {
+        Q::Enum {
+            domain,
+            indices: Box::new(Q::Long(i64::NULL)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn q(input: &str) -> Q {
+        crate::parse_one(input).unwrap()
+    }
+
+    #[test]
+    fn enumerations_resolve_against_their_domain() {
+        let e = Q::Enum {
+            domain: Symbol::from("d"),
+            indices: Box::new(q("1 0 1 5")),
+        };
+        assert_eq!(e.type_id(), 20);
+        assert_eq!(e.resolve(&q("`a`b")), q("`b`a`b`"));
+        let atom = Q::Enum {
+            domain: Symbol::from("d"),
+            indices: Box::new(Q::Long(1)),
+        };
+        assert_eq!(atom.resolve(&q("`a`b")), q("`b"));
+        assert_eq!(q("1 2").resolve(&q("`a`b")), q("1 2"));
+    }
+
+    #[test]
+    fn enumerate_grows_its_domain() {
+        let mut domain = Q::Symbols(vec![]);
+        let e = Q::enumerate(&q("`a`b`a"), Symbol::from("d"), &mut domain).unwrap();
+        assert_eq!(
+            e,
+            Q::Enum {
+                domain: Symbol::from("d"),
+                indices: Box::new(q("0 1 0")),
+            }
+        );
+        assert_eq!(domain, q("`a`b"));
+        let atom = Q::enumerate(&q("`c"), Symbol::from("d"), &mut domain).unwrap();
+        assert_eq!(atom.resolve(&domain), q("`c"));
+        assert_eq!(domain, q("`a`b`c"));
+        let mut longs = q("1 2");
+        assert_eq!(
+            Q::enumerate(&q("`a"), Symbol::from("d"), &mut longs),
+            Err(QError::Type)
+        );
+    }
+}
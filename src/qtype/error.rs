@@ -0,0 +1,23 @@
+use crate::qtype::attr::Attr;
+use miette::Diagnostic;
+use thiserror::Error;
+
+// Errors raised by operations on `Q` values, named after their q counterparts
+#[derive(Diagnostic, Debug, Clone, PartialEq, Error)]
+pub enum QError {
+    #[error("'type")]
+    #[diagnostic(help("the operation is not defined for the type of its argument(s)"))]
+    Type,
+
+    #[error("'length")]
+    #[diagnostic(help("arguments do not conform in length"))]
+    Length,
+
+    #[error("'domain")]
+    #[diagnostic(help("argument is out of the domain of the operation"))]
+    Domain,
+
+    #[error("'{}-fail", .0.code())]
+    #[diagnostic(help("the list does not satisfy the attribute"))]
+    Fail(Attr),
+}
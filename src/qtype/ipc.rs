@@ -0,0 +1,504 @@
+// kdb+ IPC wire encoding, and connections exchanging messages
+use crate::qtype::Q;
+use crate::qtype::attr::Attr;
+use crate::qtype::chrono::{Date, Minute, Month, Second, Timespan, Timestamp};
+use crate::qtype::symbol::Symbol;
+use miette::Diagnostic;
+use std::sync::mpsc::{Receiver, Sender, channel};
+use thiserror::Error;
+
+#[derive(Diagnostic, Debug, Clone, PartialEq, Error)]
+pub enum IpcError {
+    #[error("symbol {0:?} contains an embedded null byte")]
+    #[diagnostic(help("kdb+ symbols are null-terminated on the wire and cannot contain \\0"))]
+    EmbeddedNul(String),
+
+    #[error("symbol is not null-terminated")]
+    UnterminatedSymbol,
+
+    #[error("symbol is not valid utf-8")]
+    InvalidUtf8,
+
+    #[error("connection closed")]
+    Closed,
+
+    #[error("type {0} has no IPC encoding")]
+    #[diagnostic(help("enumerations are sent as their symbols; resolve them first"))]
+    UnsupportedType(i8),
+
+    #[error("message ends before its last item")]
+    Truncated,
+
+    #[error("message holds an invalid {0}")]
+    InvalidValue(&'static str),
+}
+
+/// Appends the kdb+ IPC encoding of `q`, as q's `-8!` without the message header. Vectors
+/// carry their attribute.
+pub fn write_q(buf: &mut Vec<u8>, q: &Q) -> Result<(), IpcError> {
+    write_object(buf, q, 0)
+}
+
+/// How deeply lists and dictionaries may nest in a message read by `read_q`
+const MAX_DEPTH: usize = 256;
+
+/// Reads an item encoded by `write_q` from the front of `bytes`, returning it with the
+/// remaining bytes. Items nested more than `MAX_DEPTH` deep are rejected.
+pub fn read_q(bytes: &[u8]) -> Result<(Q, &[u8]), IpcError> {
+    read_object(bytes, 0)
+}
+
+fn read_object(bytes: &[u8], depth: usize) -> Result<(Q, &[u8]), IpcError> {
+    if depth > MAX_DEPTH {
+        return Err(IpcError::InvalidValue("nesting depth"));
+    }
+    let (&type_id, rest) = bytes.split_first().ok_or(IpcError::Truncated)?;
+    match type_id as i8 {
+        0 => {
+            let (attr, len, mut rest) = vector_header(rest)?;
+            let mut items = Vec::with_capacity(len.min(rest.len()));
+            for _ in 0..len {
+                let (item, tail) = read_object(rest, depth + 1)?;
+                items.push(item);
+                rest = tail;
+            }
+            Ok((with_attr(Q::List(items), attr), rest))
+        }
+        98 => {
+            let (_, rest) = rest.split_first().ok_or(IpcError::Truncated)?;
+            match read_object(rest, depth + 1)? {
+                (Q::Dict(columns, values), rest) => match (*columns, *values) {
+                    (Q::Symbols(columns), Q::List(values)) => Ok((
+                        Q::table(columns, values).map_err(|_| IpcError::InvalidValue("table"))?,
+                        rest,
+                    )),
+                    _ => Err(IpcError::InvalidValue("table")),
+                },
+                _ => Err(IpcError::InvalidValue("table")),
+            }
+        }
+        99 => {
+            let (keys, rest) = read_object(rest, depth + 1)?;
+            let (values, rest) = read_object(rest, depth + 1)?;
+            Ok((Q::Dict(Box::new(keys), Box::new(values)), rest))
+        }
+        type_id => read_flat(type_id, rest),
+    }
+}
+
+/// Reads an atom, a simple vector or the generic null of type `type_id` from the front of
+/// `rest`. Kept apart from `read_object` so that its recursion takes little stack.
+fn read_flat(type_id: i8, rest: &[u8]) -> Result<(Q, &[u8]), IpcError> {
+    macro_rules! decode {
+        ($($id:literal => $atom:ident / $vector:ident: $t:ty),*) => {
+            match type_id {
+                $(
+                    id if id == -$id => {
+                        let (x, rest) = take::<$t>(rest)?;
+                        Ok((Q::$atom(x), rest))
+                    }
+                    $id => {
+                        let (attr, len, mut rest) = vector_header(rest)?;
+                        let mut v = Vec::with_capacity(len.min(rest.len()));
+                        for _ in 0..len {
+                            let (x, tail) = take::<$t>(rest)?;
+                            v.push(x);
+                            rest = tail;
+                        }
+                        Ok((with_attr(Q::$vector(v), attr), rest))
+                    }
+                )*
+                -11 => {
+                    let (s, rest) = read_symbol(rest)?;
+                    Ok((Q::Symbol(s), rest))
+                }
+                11 => {
+                    let (attr, len, mut rest) = vector_header(rest)?;
+                    let mut symbols = Vec::with_capacity(len.min(rest.len()));
+                    for _ in 0..len {
+                        let (s, tail) = read_symbol(rest)?;
+                        symbols.push(s);
+                        rest = tail;
+                    }
+                    Ok((with_attr(Q::Symbols(symbols), attr), rest))
+                }
+                101 => {
+                    let (_, rest) = rest.split_first().ok_or(IpcError::Truncated)?;
+                    Ok((Q::Null, rest))
+                }
+                other => Err(IpcError::UnsupportedType(other)),
+            }
+        };
+    }
+    decode!(
+        1 => Boolean / Booleans: bool,
+        2 => Guid / Guids: uuid::Uuid,
+        4 => Byte / Bytes: u8,
+        5 => Short / Shorts: i16,
+        6 => Int / Ints: i32,
+        7 => Long / Longs: i64,
+        8 => Real / Reals: f32,
+        9 => Float / Floats: f64,
+        10 => Char / String: u8,
+        12 => Timestamp / Timestamps: Timestamp,
+        13 => Month / Months: Month,
+        14 => Date / Dates: Date,
+        16 => Timespan / Timespans: Timespan,
+        17 => Minute / Minutes: Minute,
+        18 => Second / Seconds: Second
+    )
+}
+
+/// The kdb+ IPC message holding `q`: an 8-byte little-endian header, then `q` as `write_q`
+pub fn encode_message(q: &Q) -> Result<Vec<u8>, IpcError> {
+    let mut buf = vec![1, 0, 0, 0, 0, 0, 0, 0];
+    write_q(&mut buf, q)?;
+    let size = i32::try_from(buf.len()).map_err(|_| IpcError::InvalidValue("message size"))?;
+    buf[4..8].copy_from_slice(&size.to_le_bytes());
+    Ok(buf)
+}
+
+/// The item of a message made by `encode_message`
+pub fn decode_message(bytes: &[u8]) -> Result<Q, IpcError> {
+    let (header, body) = bytes.split_at_checked(8).ok_or(IpcError::Truncated)?;
+    if message_size(header)? != bytes.len() {
+        return Err(IpcError::InvalidValue("message size"));
+    }
+    match read_q(body)? {
+        (q, []) => Ok(q),
+        _ => Err(IpcError::InvalidValue("message size")),
+    }
+}
+
+/// The size of a whole message, from its 8-byte header
+pub(crate) fn message_size(header: &[u8]) -> Result<usize, IpcError> {
+    let header = header.get(..8).ok_or(IpcError::Truncated)?;
+    if header[0] != 1 {
+        return Err(IpcError::InvalidValue("byte order"));
+    }
+    if header[2] != 0 {
+        return Err(IpcError::InvalidValue("compression flag"));
+    }
+    let size = i32::from_le_bytes(header[4..8].try_into().unwrap());
+    usize::try_from(size).map_err(|_| IpcError::InvalidValue("message size"))
+}
+
+/// One end of a connection to a q process
+pub trait Connection {
+    /// Sends `message` to the other end
+    fn send(&mut self, message: &Q) -> Result<(), IpcError>;
+
+    /// Waits for the next message from the other end
+    fn recv(&mut self) -> Result<Q, IpcError>;
+}
+
+/// One end of an in-memory connection made by `loopback`
+#[derive(Debug)]
+pub struct Loopback {
+    outgoing: Sender<Q>,
+    incoming: Receiver<Q>,
+}
+
+/// A pair of connected in-memory endpoints, so code talking to a q process can run without
+/// one: what one end sends, the other receives. Once an end is dropped, the other fails with
+/// `IpcError::Closed`.
+pub fn loopback() -> (Loopback, Loopback) {
+    let (to_right, from_left) = channel();
+    let (to_left, from_right) = channel();
+    let left = Loopback {
+        outgoing: to_right,
+        incoming: from_right,
+    };
+    let right = Loopback {
+        outgoing: to_left,
+        incoming: from_left,
+    };
+    (left, right)
+}
+
+impl Connection for Loopback {
+    fn send(&mut self, message: &Q) -> Result<(), IpcError> {
+        self.outgoing
+            .send(message.clone())
+            .map_err(|_| IpcError::Closed)
+    }
+
+    fn recv(&mut self) -> Result<Q, IpcError> {
+        self.incoming.recv().map_err(|_| IpcError::Closed)
+    }
+}
+
+/// Appends `symbol` as a null-terminated string
+pub fn write_symbol(buf: &mut Vec<u8>, symbol: Symbol) -> Result<(), IpcError> {
+    let text = symbol.resolve();
+    if text.contains('\0') {
+        return Err(IpcError::EmbeddedNul(text.to_string()));
+    }
+    buf.extend_from_slice(text.as_bytes());
+    buf.push(0);
+    Ok(())
+}
+
+/// Reads a null-terminated symbol from the front of `bytes`, returning it with the remaining bytes
+pub fn read_symbol(bytes: &[u8]) -> Result<(Symbol, &[u8]), IpcError> {
+    let end = bytes
+        .iter()
+        .position(|&b| b == 0)
+        .ok_or(IpcError::UnterminatedSymbol)?;
+    let text = std::str::from_utf8(&bytes[..end]).map_err(|_| IpcError::InvalidUtf8)?;
+    Ok((Symbol::from(text), &bytes[end + 1..]))
+}
+
+fn write_object(buf: &mut Vec<u8>, q: &Q, attr: u8) -> Result<(), IpcError> {
+    let type_id = q.type_id();
+    let header = |buf: &mut Vec<u8>, len: usize| -> Result<(), IpcError> {
+        let len = i32::try_from(len).map_err(|_| IpcError::InvalidValue("list count"))?;
+        buf.extend([type_id as u8, attr]);
+        buf.extend_from_slice(&len.to_le_bytes());
+        Ok(())
+    };
+    macro_rules! encode {
+        ($($atom:ident / $vector:ident),*) => {
+            match q {
+                $(
+                    Q::$atom(x) => {
+                        buf.push(type_id as u8);
+                        x.put(buf);
+                    }
+                    Q::$vector(v) => {
+                        header(buf, v.len())?;
+                        v.iter().for_each(|x| x.put(buf));
+                    }
+                )*
+                Q::Symbol(s) => {
+                    buf.push(type_id as u8);
+                    write_symbol(buf, *s)?;
+                }
+                Q::Symbols(v) => {
+                    header(buf, v.len())?;
+                    for s in v {
+                        write_symbol(buf, *s)?;
+                    }
+                }
+                Q::List(items) => {
+                    header(buf, items.len())?;
+                    for item in items {
+                        write_q(buf, item)?;
+                    }
+                }
+                Q::Dict(keys, values) => {
+                    buf.push(type_id as u8);
+                    write_q(buf, keys)?;
+                    write_q(buf, values)?;
+                }
+                Q::Table(columns, values) => {
+                    buf.extend([type_id as u8, 0, 99]);
+                    write_q(buf, &Q::Symbols(columns.clone()))?;
+                    write_q(buf, &Q::List(values.clone()))?;
+                }
+                Q::Attributed(attr, list) => write_object(buf, list, attr_byte(*attr))?,
+                Q::Enum { .. } => return Err(IpcError::UnsupportedType(type_id)),
+                Q::Null => buf.extend([type_id as u8, 0]),
+            }
+        };
+    }
+    encode!(
+        Boolean / Booleans,
+        Guid / Guids,
+        Byte / Bytes,
+        Short / Shorts,
+        Int / Ints,
+        Long / Longs,
+        Real / Reals,
+        Float / Floats,
+        Char / String,
+        Timestamp / Timestamps,
+        Month / Months,
+        Date / Dates,
+        Timespan / Timespans,
+        Minute / Minutes,
+        Second / Seconds
+    );
+    Ok(())
+}
+
+/// The attribute byte, count and remaining bytes after the type of a vector
+fn vector_header(bytes: &[u8]) -> Result<(u8, usize, &[u8]), IpcError> {
+    let (&attr, rest) = bytes.split_first().ok_or(IpcError::Truncated)?;
+    let (len, rest) = take::<i32>(rest)?;
+    let len = usize::try_from(len).map_err(|_| IpcError::InvalidValue("list count"))?;
+    Ok((attr, len, rest))
+}
+
+fn attr_byte(attr: Attr) -> u8 {
+    match attr {
+        Attr::Sorted => 1,
+        Attr::Unique => 2,
+        Attr::Parted => 3,
+        Attr::Grouped => 4,
+    }
+}
+
+/// The attribute of a vector's attribute byte, if it has one
+pub(crate) fn byte_attr(attr: u8) -> Option<Attr> {
+    match attr {
+        1 => Some(Attr::Sorted),
+        2 => Some(Attr::Unique),
+        3 => Some(Attr::Parted),
+        4 => Some(Attr::Grouped),
+        _ => None,
+    }
+}
+
+/// `list` with the attribute of the byte `attr`, which is trusted rather than checked
+fn with_attr(list: Q, attr: u8) -> Q {
+    match byte_attr(attr) {
+        Some(attr) => Q::Attributed(attr, Box::new(list)),
+        None => list,
+    }
+}
+
+fn take<T: Wire>(bytes: &[u8]) -> Result<(T, &[u8]), IpcError> {
+    let (item, rest) = bytes.split_at_checked(T::SIZE).ok_or(IpcError::Truncated)?;
+    let item = T::get(item).ok_or(IpcError::InvalidValue(std::any::type_name::<T>()))?;
+    Ok((item, rest))
+}
+
+/// Fixed-size items of vectors, as little-endian bytes
+trait Wire: Sized {
+    const SIZE: usize;
+
+    fn put(&self, buf: &mut Vec<u8>);
+
+    /// Decodes exactly `SIZE` bytes
+    fn get(bytes: &[u8]) -> Option<Self>;
+}
+
+macro_rules! impl_wire_number {
+    ($($t:ty),*) => {
+        $(
+            impl Wire for $t {
+                const SIZE: usize = size_of::<$t>();
+
+                fn put(&self, buf: &mut Vec<u8>) {
+                    buf.extend_from_slice(&self.to_le_bytes());
+                }
+
+                fn get(bytes: &[u8]) -> Option<Self> {
+                    Some(<$t>::from_le_bytes(bytes.try_into().ok()?))
+                }
+            }
+        )*
+    };
+}
+
+impl_wire_number!(u8, i16, i32, i64, f32, f64);
+
+impl Wire for bool {
+    const SIZE: usize = 1;
+
+    fn put(&self, buf: &mut Vec<u8>) {
+        buf.push(*self as u8);
+    }
+
+    fn get(bytes: &[u8]) -> Option<Self> {
+        Some(bytes[0] != 0)
+    }
+}
+
+impl Wire for uuid::Uuid {
+    const SIZE: usize = 16;
+
+    fn put(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(self.as_bytes());
+    }
+
+    fn get(bytes: &[u8]) -> Option<Self> {
+        uuid::Uuid::from_slice(bytes).ok()
+    }
+}
+
+// temporal types travel as their underlying ints
+macro_rules! impl_wire_temporal {
+    ($($t:ty: $int:ty, $to:ident, $from:expr);*) => {
+        $(
+            impl Wire for $t {
+                const SIZE: usize = size_of::<$int>();
+
+                fn put(&self, buf: &mut Vec<u8>) {
+                    self.$to().put(buf);
+                }
+
+                fn get(bytes: &[u8]) -> Option<Self> {
+                    $from(<$int>::get(bytes)?)
+                }
+            }
+        )*
+    };
+}
+
+impl_wire_temporal!(
+    Timestamp: i64, to_i64, |n| Some(Timestamp::from_i64(n));
+    Timespan: i64, to_i64, |n| Some(Timespan::from_i64(n));
+    Month: i32, to_i32, Month::from_raw;
+    Date: i32, to_i32, Date::from_raw;
+    Minute: i32, to_i32, Minute::from_raw;
+    Second: i32, to_i32, Second::from_raw
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn symbols_round_trip_null_terminated() {
+        let mut buf = Vec::new();
+        write_symbol(&mut buf, Symbol::from("abc")).unwrap();
+        assert_eq!(buf, b"abc\0");
+        assert_eq!(read_symbol(&buf), Ok((Symbol::from("abc"), &[][..])));
+        assert_eq!(read_symbol(b"abc"), Err(IpcError::UnterminatedSymbol));
+    }
+
+    #[test]
+    fn symbols_with_embedded_nul_are_rejected() {
+        let symbol = Symbol::from("a\0b");
+        let mut buf = Vec::new();
+        assert_eq!(
+            write_symbol(&mut buf, symbol),
+            Err(IpcError::EmbeddedNul("a\0b".to_string()))
+        );
+        assert_eq!(
+            encode_message(&Q::Symbols(vec![Symbol::from("a"), symbol])),
+            Err(IpcError::EmbeddedNul("a\0b".to_string()))
+        );
+    }
+
+    #[test]
+    fn loopback_delivers_to_the_other_end() {
+        let (mut left, mut right) = loopback();
+        let longs = Q::Longs(vec![1, 2, 3]);
+        left.send(&longs).unwrap();
+        assert_eq!(right.recv(), Ok(longs));
+        right.send(&Q::Long(4)).unwrap();
+        assert_eq!(left.recv(), Ok(Q::Long(4)));
+        drop(right);
+        assert_eq!(left.recv(), Err(IpcError::Closed));
+    }
+
+    #[test]
+    fn deeply_nested_lists_are_rejected() {
+        let nested = |depth| {
+            let mut q = Q::Long(1);
+            for _ in 0..depth {
+                q = Q::List(vec![q]);
+            }
+            q
+        };
+        let mut buf = Vec::new();
+        write_q(&mut buf, &nested(MAX_DEPTH)).unwrap();
+        assert_eq!(read_q(&buf), Ok((nested(MAX_DEPTH), &[][..])));
+        let mut buf = Vec::new();
+        write_q(&mut buf, &nested(MAX_DEPTH + 1)).unwrap();
+        assert_eq!(read_q(&buf), Err(IpcError::InvalidValue("nesting depth")));
+    }
+}
@@ -0,0 +1,786 @@
+// Construction and manipulation of q lists
+use crate::qtype::attr::Attr;
+use crate::qtype::chrono::{Date, Minute, Month, Second, Timespan, Timestamp};
+use crate::qtype::null::Nullable;
+use crate::qtype::symbol::Symbol;
+use crate::qtype::{Q, QError};
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+impl Q {
+    /// A vector of `n` copies of `atom`, like q's `n#atom`
+    pub fn fill_vector(atom: &Q, n: usize) -> Result<Q, QError> {
+        macro_rules! fill {
+            ($($atom:ident => $vector:ident),*) => {
+                match atom {
+                    $(Q::$atom(x) => Ok(Q::$vector(vec![*x; n])),)*
+                    _ => Err(QError::Type),
+                }
+            };
+        }
+        fill!(
+            Boolean => Booleans,
+            Guid => Guids,
+            Byte => Bytes,
+            Short => Shorts,
+            Int => Ints,
+            Long => Longs,
+            Real => Reals,
+            Float => Floats,
+            Char => String,
+            Symbol => Symbols,
+            Timestamp => Timestamps,
+            Month => Months,
+            Date => Dates,
+            Timespan => Timespans,
+            Minute => Minutes,
+            Second => Seconds
+        )
+    }
+}
+
+impl Q {
+    /// The empty list of the given type, e.g. `` `long$() `` for 7. Type 0 gives the empty
+    /// general list.
+    pub fn empty(type_id: i8) -> Result<Q, QError> {
+        Ok(match type_id {
+            0 => Q::List(vec![]),
+            1 => Q::Booleans(vec![]),
+            2 => Q::Guids(vec![]),
+            4 => Q::Bytes(vec![]),
+            5 => Q::Shorts(vec![]),
+            6 => Q::Ints(vec![]),
+            7 => Q::Longs(vec![]),
+            8 => Q::Reals(vec![]),
+            9 => Q::Floats(vec![]),
+            10 => Q::String(vec![]),
+            11 => Q::Symbols(vec![]),
+            12 => Q::Timestamps(vec![]),
+            13 => Q::Months(vec![]),
+            14 => Q::Dates(vec![]),
+            16 => Q::Timespans(vec![]),
+            17 => Q::Minutes(vec![]),
+            18 => Q::Seconds(vec![]),
+            _ => return Err(QError::Type),
+        })
+    }
+
+    /// q's `enlist`: a one-item list holding `self`. An atom gives a one-item simple vector,
+    /// which is never equal to the atom itself.
+    pub fn enlist(self) -> Q {
+        if self.is_atom()
+            && let Ok(vector) = Q::fill_vector(&self, 1)
+        {
+            return vector;
+        }
+        Q::List(vec![self])
+    }
+
+    /// `self` as a general list: an atom, dictionary or table becomes a one-item list, a simple
+    /// vector a list of its atoms, and a general list is returned as is. The inverse of
+    /// collapsing a list of like atoms into a vector.
+    pub fn to_list(&self) -> Q {
+        match self.without_attr_ref() {
+            Q::List(_) => self.without_attr_ref().clone(),
+            list => match list.items() {
+                Ok(items) if !list.is_atom() => Q::List(items),
+                _ => Q::List(vec![list.clone()]),
+            },
+        }
+    }
+
+    /// q's `n rotate x`: the items of `self` shifted left by `n`, wrapping around. A rotated
+    /// list is no longer sorted or parted, but stays unique.
+    pub fn rotate(&self, n: i64) -> Result<Q, QError> {
+        let len = self.len() as i64;
+        let shift = if len == 0 {
+            0
+        } else {
+            n.rem_euclid(len) as usize
+        };
+        let idx: Vec<usize> = (0..self.len()).map(|i| (i + shift) % self.len()).collect();
+        self.reorder(&idx, |attr| shift == 0 || survives_rotation(attr))
+    }
+
+    /// q's `reverse`, which keeps runs of equal items together but reverses their order
+    pub fn reverse(&self) -> Result<Q, QError> {
+        let idx: Vec<usize> = (0..self.len()).rev().collect();
+        self.reorder(&idx, |attr| self.len() < 2 || attr != Attr::Sorted)
+    }
+
+    /// q's `n#x`: the first `n` items of `self`, or the last `-n` if `n` is negative, cycling
+    /// through `self` when `n` exceeds its count. An atom is repeated `n` times.
+    pub fn take(&self, n: i64) -> Result<Q, QError> {
+        if self.is_atom() {
+            return Q::fill_vector(self, n.unsigned_abs() as usize);
+        }
+        let (len, count) = (self.len(), n.unsigned_abs() as usize);
+        if len == 0 {
+            return match self.without_attr_ref() {
+                Q::List(_) => Ok(Q::List(vec![Q::List(vec![]); count])),
+                list => Q::fill_vector(&list.null_item(), count),
+            };
+        }
+        let start = if n < 0 { (len - count % len) % len } else { 0 };
+        let idx: Vec<usize> = (0..count).map(|i| (start + i) % len).collect();
+        // a prefix or suffix is a contiguous slice, which preserves every attribute
+        self.reorder(&idx, |attr| count <= len || attr == Attr::Grouped)
+    }
+
+    /// `x where mask`: the items of `self` whose flag in `mask` is set. A selection keeps the
+    /// relative order of items, so any attribute still holds.
+    pub fn select(&self, mask: &[bool]) -> Result<Q, QError> {
+        if mask.len() != self.len() {
+            return Err(QError::Length);
+        }
+        let idx: Vec<usize> = (0..mask.len()).filter(|&i| mask[i]).collect();
+        self.reorder(&idx, |_| true)
+    }
+
+    /// q's `sublist`: `n sublist x` is the first `n` items of `self`, or the last `-n` if `n` is
+    /// negative, and `(i;n) sublist x` the `n` items from index `i`. Unlike `#`, it stops at the
+    /// end of `self` rather than cycling.
+    pub fn sublist(&self, spec: &Q) -> Result<Q, QError> {
+        if self.is_atom() || matches!(self, Q::Dict(..) | Q::Table(..)) {
+            return Err(QError::Type);
+        }
+        let len = self.len();
+        let range = if spec.is_atom() {
+            let n = integer(spec)?;
+            let count = (n.unsigned_abs() as usize).min(len);
+            if n < 0 { len - count..len } else { 0..count }
+        } else {
+            match spec.items()?.as_slice() {
+                [start, count] => {
+                    let (start, count) = (integer(start)?, integer(count)?);
+                    if start < 0 || count < 0 {
+                        return Err(QError::Domain);
+                    }
+                    let start = (start as usize).min(len);
+                    start..start.saturating_add(count as usize).min(len)
+                }
+                _ => return Err(QError::Length),
+            }
+        };
+        let idx: Vec<usize> = range.collect();
+        self.reorder(&idx, |_| true)
+    }
+
+    /// The longest prefix of `self` whose items, as atoms, all satisfy `f`, e.g. taking while
+    /// less than 3 from `1 2 3 1` gives `1 2`
+    pub fn take_while<F: Fn(&Q) -> bool>(&self, f: F) -> Result<Q, QError> {
+        let n = self.prefix_len(f)?;
+        self.reorder(&(0..n).collect::<Vec<_>>(), |_| true)
+    }
+
+    /// `self` without the longest prefix whose items all satisfy `f`, see `take_while`
+    pub fn drop_while<F: Fn(&Q) -> bool>(&self, f: F) -> Result<Q, QError> {
+        let n = self.prefix_len(f)?;
+        self.reorder(&(n..self.len()).collect::<Vec<_>>(), |_| true)
+    }
+
+    /// q's `prev`: each item's predecessor, with the typed null first, e.g. `prev 1 2 3` is
+    /// `0N 1 2`
+    pub fn prev(&self) -> Result<Q, QError> {
+        self.shifted(-1)
+    }
+
+    /// q's `next`: each item's successor, with the typed null last, e.g. `next 1 2 3` is
+    /// `2 3 0N`
+    pub fn next(&self) -> Result<Q, QError> {
+        self.shifted(1)
+    }
+
+    /// q's `differ`: flags the items which do not match their predecessor, with the first
+    /// always flagged, e.g. `differ 1 1 2 2 3` is `10101b`. Nulls match each other.
+    pub fn differ(&self) -> Result<Q, QError> {
+        if self.is_atom() || matches!(self, Q::Dict(..) | Q::Table(..)) {
+            return Err(QError::Type);
+        }
+        let items = self.items()?;
+        Ok(Q::Booleans(
+            (0..items.len())
+                .map(|i| i == 0 || !matches_item(&items[i - 1], &items[i]))
+                .collect(),
+        ))
+    }
+
+    /// q's `x cross y`: every pair of an item of `self` with an item of `other`, in row-major
+    /// order, as a general list of two-item lists. Pairs of atoms of one type are simple
+    /// vectors, as in q, so `1 2 cross 3 4` is `(1 3;1 4;2 3;2 4)`. Atoms count as one-item
+    /// lists.
+    pub fn cross(&self, other: &Q) -> Result<Q, QError> {
+        let (lhs, rhs) = (self.items()?, other.items()?);
+        Ok(Q::List(
+            lhs.iter()
+                .flat_map(|x| {
+                    rhs.iter()
+                        .map(move |y| Q::collapse(vec![x.clone(), y.clone()]))
+                })
+                .collect(),
+        ))
+    }
+
+    /// q's each-both `x f' y`: `f` applied to the items of `self` and `other` pairwise. An
+    /// atom on either side is paired with every item of the other. Results of one atomic type
+    /// collect into a simple vector.
+    pub fn zip_with<F: Fn(Q, Q) -> Q>(&self, other: &Q, f: F) -> Result<Q, QError> {
+        if self.is_atom() && other.is_atom() {
+            return Ok(f(self.clone(), other.clone()));
+        }
+        let (lhs, rhs) = (self.items()?, other.items()?);
+        let len = match (self.is_atom(), other.is_atom()) {
+            (true, _) => rhs.len(),
+            (_, true) => lhs.len(),
+            _ if lhs.len() == rhs.len() => lhs.len(),
+            _ => return Err(QError::Length),
+        };
+        let item = |items: &[Q], i: usize| items[if items.len() == len { i } else { 0 }].clone();
+        Ok(Q::collapse(
+            (0..len).map(|i| f(item(&lhs, i), item(&rhs, i))).collect(),
+        ))
+    }
+
+    /// q's over `f/`: `f` applied left to right between the items of `self`, e.g. `+/ 1 2 3 4`
+    /// is `10`. With a `seed`, the first application is to the seed and the first item, as
+    /// `seed f/ x`. An empty list with no seed stays empty, and an atom is a one-item list.
+    pub fn fold<F: Fn(Q, Q) -> Q>(&self, seed: Option<Q>, f: F) -> Result<Q, QError> {
+        let mut items = self.items()?.into_iter();
+        let Some(first) = seed.or_else(|| items.next()) else {
+            return Ok(self.clone());
+        };
+        Ok(items.fold(first, f))
+    }
+
+    /// q's scan `f\`: like `fold`, but the result after each item, so `+\ 1 2 3 4` is
+    /// `1 3 6 10`. With a `seed` there is still one result per item, as `seed f\ x`, and an
+    /// empty list stays empty.
+    pub fn scan<F: Fn(Q, Q) -> Q>(&self, seed: Option<Q>, f: F) -> Result<Q, QError> {
+        let items = self.items()?;
+        let (mut acc, rest, mut results) = match (seed, items.split_first()) {
+            (_, None) => return Ok(self.clone()),
+            (Some(seed), _) => (seed, items.as_slice(), vec![]),
+            (None, Some((first, rest))) => (first.clone(), rest, vec![first.clone()]),
+        };
+        for item in rest {
+            acc = f(acc, item.clone());
+            results.push(acc.clone());
+        }
+        Ok(Q::collapse(results))
+    }
+
+    /// q's `flip` on a general list of lists: rows become columns, so `flip (1 2 3;4 5 6)` is
+    /// `(1 4;2 5;3 6)`. The lists must have the same count, and an atom is repeated down its
+    /// column.
+    pub fn flip_list(&self) -> Result<Q, QError> {
+        let Q::List(lists) = self.without_attr_ref() else {
+            return Err(QError::Type);
+        };
+        let rows = lists.iter().map(Q::items).collect::<Result<Vec<_>, _>>()?;
+        let mut counts = lists
+            .iter()
+            .zip(&rows)
+            .filter(|(row, _)| !row.is_atom())
+            .map(|(_, items)| items.len());
+        let Some(len) = counts.next() else {
+            return Err(QError::Type);
+        };
+        if counts.any(|count| count != len) {
+            return Err(QError::Length);
+        }
+        let item = |items: &[Q], i: usize| items[if items.len() == len { i } else { 0 }].clone();
+        Ok(Q::List(
+            (0..len)
+                .map(|i| Q::collapse(rows.iter().map(|row| item(row, i)).collect()))
+                .collect(),
+        ))
+    }
+
+    /// q's `fills`: each null replaced by the latest non-null item before it. Leading nulls
+    /// have nothing to take and stay null.
+    pub fn fills(&self) -> Result<Q, QError> {
+        Ok(match self.without_attr_ref() {
+            Q::Shorts(v) => Q::Shorts(fills(v)),
+            Q::Ints(v) => Q::Ints(fills(v)),
+            Q::Longs(v) => Q::Longs(fills(v)),
+            Q::Reals(v) => Q::Reals(fills(v)),
+            Q::Floats(v) => Q::Floats(fills(v)),
+            Q::Timestamps(v) => Q::Timestamps(fills(v)),
+            Q::Months(v) => Q::Months(fills(v)),
+            Q::Dates(v) => Q::Dates(fills(v)),
+            Q::Timespans(v) => Q::Timespans(fills(v)),
+            Q::Minutes(v) => Q::Minutes(fills(v)),
+            Q::Seconds(v) => Q::Seconds(fills(v)),
+            _ => return Err(QError::Type),
+        })
+    }
+
+    // Helper methods
+    /// The items of `self` taken `offset` positions ahead, with nulls where that falls outside
+    fn shifted(&self, offset: i64) -> Result<Q, QError> {
+        let len = self.len();
+        let source = |i: usize| usize::try_from(i as i64 + offset).ok().filter(|&j| j < len);
+        macro_rules! shift {
+            ($($vector:ident => $atom:ident),*) => {
+                match (self.without_attr_ref(), self.null_item()) {
+                    $(
+                        (Q::$vector(v), Q::$atom(null)) => Q::$vector(
+                            (0..len).map(|i| source(i).map_or(null, |j| v[j])).collect(),
+                        ),
+                    )*
+                    (Q::List(v), null) => Q::List(
+                        (0..len)
+                            .map(|i| source(i).map_or(null.clone(), |j| v[j].clone()))
+                            .collect(),
+                    ),
+                    _ => return Err(QError::Type),
+                }
+            };
+        }
+        Ok(shift!(
+            Booleans => Boolean,
+            Guids => Guid,
+            Bytes => Byte,
+            Shorts => Short,
+            Ints => Int,
+            Longs => Long,
+            Reals => Real,
+            Floats => Float,
+            String => Char,
+            Symbols => Symbol,
+            Timestamps => Timestamp,
+            Months => Month,
+            Dates => Date,
+            Timespans => Timespan,
+            Minutes => Minute,
+            Seconds => Second
+        ))
+    }
+
+    fn prefix_len(&self, f: impl Fn(&Q) -> bool) -> Result<usize, QError> {
+        if self.is_atom() || matches!(self, Q::Dict(..) | Q::Table(..) | Q::Null) {
+            return Err(QError::Type);
+        }
+        for i in 0..self.len() {
+            if !f(&self.at(i).ok_or(QError::Length)?) {
+                return Ok(i);
+            }
+        }
+        Ok(self.len())
+    }
+
+    /// The items of a list, or the atom itself
+    pub(crate) fn items(&self) -> Result<Vec<Q>, QError> {
+        if self.is_atom() {
+            return Ok(vec![self.clone()]);
+        }
+        if matches!(self, Q::Dict(..) | Q::Table(..) | Q::Null) {
+            return Err(QError::Type);
+        }
+        (0..self.len())
+            .map(|i| self.at(i).ok_or(QError::Length))
+            .collect()
+    }
+
+    /// A list of `items`, which is a simple vector when they are all atoms of one type
+    pub(crate) fn collapse(items: Vec<Q>) -> Q {
+        macro_rules! collapse {
+            ($($atom:ident => $vector:ident),*) => {
+                match items.first() {
+                    $(
+                        Some(Q::$atom(_)) if items.iter().all(|q| matches!(q, Q::$atom(_))) => {
+                            Q::$vector(
+                                items
+                                    .iter()
+                                    .map(|q| match q {
+                                        Q::$atom(x) => *x,
+                                        _ => unreachable!(),
+                                    })
+                                    .collect(),
+                            )
+                        }
+                    )*
+                    _ => Q::List(items),
+                }
+            };
+        }
+        collapse!(
+            Boolean => Booleans,
+            Guid => Guids,
+            Byte => Bytes,
+            Short => Shorts,
+            Int => Ints,
+            Long => Longs,
+            Real => Reals,
+            Float => Floats,
+            Char => String,
+            Symbol => Symbols,
+            Timestamp => Timestamps,
+            Month => Months,
+            Date => Dates,
+            Timespan => Timespans,
+            Minute => Minutes,
+            Second => Seconds
+        )
+    }
+
+    pub(crate) fn without_attr_ref(&self) -> &Q {
+        match self {
+            Q::Attributed(_, list) => list,
+            list => list,
+        }
+    }
+
+    /// The items of `self` at `idx`, keeping its attribute only when `keep` says the
+    /// rearrangement preserves it
+    pub(crate) fn reorder(&self, idx: &[usize], keep: impl Fn(Attr) -> bool) -> Result<Q, QError> {
+        macro_rules! pick {
+            ($($vector:ident),*) => {
+                match self.without_attr_ref() {
+                    $(Q::$vector(v) => Q::$vector(idx.iter().map(|&i| v[i].clone()).collect()),)*
+                    _ => return Err(QError::Type),
+                }
+            };
+        }
+        let list = pick!(
+            Booleans, Guids, Bytes, Shorts, Ints, Longs, Reals, Floats, String, Symbols,
+            Timestamps, Months, Dates, Timespans, Minutes, Seconds, List
+        );
+        Ok(match self.attr() {
+            Some(attr) if keep(attr) => Q::Attributed(attr, Box::new(list)),
+            _ => list,
+        })
+    }
+}
+
+fn integer(q: &Q) -> Result<i64, QError> {
+    match q {
+        Q::Short(x) => Ok(*x as i64),
+        Q::Int(x) => Ok(*x as i64),
+        Q::Long(x) => Ok(*x),
+        _ => Err(QError::Type),
+    }
+}
+
+fn fills<T: Nullable>(v: &[T]) -> Vec<T> {
+    let mut last = T::NULL;
+    v.iter()
+        .map(|&x| {
+            if !x.is_null() {
+                last = x;
+            }
+            last
+        })
+        .collect()
+}
+
+/// q's match on two items, under which float nulls are equal
+pub(crate) fn matches_item(a: &Q, b: &Q) -> bool {
+    fn floats_match<T: Copy + Into<f64>>(x: &[T], y: &[T]) -> bool {
+        x.len() == y.len()
+            && x.iter().zip(y).all(|(&x, &y)| {
+                let (x, y) = (x.into(), y.into());
+                x == y || (x.is_nan() && y.is_nan())
+            })
+    }
+    match (a, b) {
+        (Q::Real(x), Q::Real(y)) => floats_match(&[*x], &[*y]),
+        (Q::Float(x), Q::Float(y)) => floats_match(&[*x], &[*y]),
+        (Q::Reals(x), Q::Reals(y)) => floats_match(x, y),
+        (Q::Floats(x), Q::Floats(y)) => floats_match(x, y),
+        (Q::List(x), Q::List(y)) => {
+            x.len() == y.len() && x.iter().zip(y).all(|(x, y)| matches_item(x, y))
+        }
+        // records and rows of tables with float nulls, as `hash_item` treats them
+        (Q::Dict(ka, va), Q::Dict(kb, vb)) => matches_item(ka, kb) && matches_item(va, vb),
+        (Q::Table(ca, va), Q::Table(cb, vb)) => {
+            ca == cb && va.len() == vb.len() && va.iter().zip(vb).all(|(a, b)| matches_item(a, b))
+        }
+        _ => a == b,
+    }
+}
+
+/// An item hashed by its content and compared with `matches_item`, so that items can be
+/// counted and grouped in one pass
+pub(crate) struct ItemKey<'a>(pub(crate) &'a Q);
+
+impl PartialEq for ItemKey<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        matches_item(self.0, other.0)
+    }
+}
+
+impl Eq for ItemKey<'_> {}
+
+impl Hash for ItemKey<'_> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        hash_item(self.0, state)
+    }
+}
+
+fn hash_item<H: Hasher>(q: &Q, state: &mut H) {
+    // every null is the same item, and 0.0 matches -0.0
+    fn float_bits(x: f64) -> u64 {
+        if x.is_nan() {
+            u64::MAX
+        } else if x == 0.0 {
+            0
+        } else {
+            x.to_bits()
+        }
+    }
+    macro_rules! hash {
+        ($($atom:ident / $vector:ident),*) => {
+            match q {
+                $(
+                    Q::$atom(x) => x.hash(state),
+                    Q::$vector(v) => v.hash(state),
+                )*
+                Q::Real(x) => float_bits(*x as f64).hash(state),
+                Q::Float(x) => float_bits(*x).hash(state),
+                Q::Reals(v) => v.iter().for_each(|&x| float_bits(x as f64).hash(state)),
+                Q::Floats(v) => v.iter().for_each(|&x| float_bits(x).hash(state)),
+                Q::List(items) => items.iter().for_each(|item| hash_item(item, state)),
+                Q::Dict(keys, values) => {
+                    hash_item(keys, state);
+                    hash_item(values, state);
+                }
+                Q::Table(columns, values) => {
+                    columns.hash(state);
+                    values.iter().for_each(|column| hash_item(column, state));
+                }
+                Q::Enum { domain, indices } => {
+                    domain.hash(state);
+                    hash_item(indices, state);
+                }
+                Q::Attributed(attr, list) => {
+                    attr.hash(state);
+                    hash_item(list, state);
+                }
+                Q::Null => {}
+            }
+        };
+    }
+    q.type_id().hash(state);
+    q.len().hash(state);
+    hash!(
+        Boolean / Booleans,
+        Guid / Guids,
+        Byte / Bytes,
+        Short / Shorts,
+        Int / Ints,
+        Long / Longs,
+        Char / String,
+        Symbol / Symbols,
+        Timestamp / Timestamps,
+        Month / Months,
+        Date / Dates,
+        Timespan / Timespans,
+        Minute / Minutes,
+        Second / Seconds
+    )
+}
+
+/// The index of the first of each distinct item of `items`, in order, and for each item the
+/// position of its group among those firsts. Items are distinct as under q's match.
+pub(crate) fn group_items(items: &[Q]) -> (Vec<usize>, Vec<usize>) {
+    let mut seen: HashMap<ItemKey, usize> = HashMap::with_capacity(items.len());
+    let mut firsts = Vec::new();
+    let group = items
+        .iter()
+        .enumerate()
+        .map(|(i, item)| {
+            *seen.entry(ItemKey(item)).or_insert_with(|| {
+                firsts.push(i);
+                firsts.len() - 1
+            })
+        })
+        .collect();
+    (firsts, group)
+}
+
+fn survives_rotation(attr: Attr) -> bool {
+    matches!(attr, Attr::Unique | Attr::Grouped)
+}
+
+// collecting items into the matching simple vector, or a general list for `Q` items
+macro_rules! impl_from_iter {
+    ($($item:ty => $vector:ident),*) => {
+        $(
+            impl FromIterator<$item> for Q {
+                fn from_iter<I: IntoIterator<Item = $item>>(iter: I) -> Self {
+                    Q::$vector(iter.into_iter().collect())
+                }
+            }
+        )*
+    };
+}
+
+impl_from_iter!(
+    bool => Booleans,
+    uuid::Uuid => Guids,
+    u8 => Bytes,
+    i16 => Shorts,
+    i32 => Ints,
+    i64 => Longs,
+    f32 => Reals,
+    f64 => Floats,
+    Symbol => Symbols,
+    Timestamp => Timestamps,
+    Month => Months,
+    Date => Dates,
+    Timespan => Timespans,
+    Minute => Minutes,
+    Second => Seconds,
+    Q => List
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn q(input: &str) -> Q {
+        crate::parse_one(input).unwrap()
+    }
+
+    #[test]
+    fn fill_vector_repeats_an_atom() {
+        assert_eq!(Q::fill_vector(&Q::Long(0), 5), Ok(Q::Longs(vec![0; 5])));
+        assert_eq!(Q::fill_vector(&q("`a"), 3), Ok(q("`a`a`a")));
+        assert_eq!(Q::fill_vector(&Q::Long(7), 0), Ok(Q::Longs(vec![])));
+        assert_eq!(Q::fill_vector(&q("1 2"), 2), Err(QError::Type));
+    }
+
+    #[test]
+    fn rearranging_drops_attributes_that_no_longer_hold() {
+        let sorted = Q::Attributed(Attr::Sorted, Box::new(q("1 2 3")));
+        assert_eq!(sorted.rotate(1), Ok(q("2 3 1")));
+        assert_eq!(sorted.rotate(3).unwrap().attr(), Some(Attr::Sorted));
+        assert_eq!(sorted.reverse(), Ok(q("3 2 1")));
+        assert_eq!(sorted.take(5), Ok(q("1 2 3 1 2")));
+        assert_eq!(sorted.take(2).unwrap().attr(), Some(Attr::Sorted));
+        assert_eq!(
+            sorted.select(&[true, false, true]).unwrap().attr(),
+            Some(Attr::Sorted)
+        );
+        let unique = Q::Attributed(Attr::Unique, Box::new(q("3 1 2")));
+        assert_eq!(unique.rotate(1).unwrap().attr(), Some(Attr::Unique));
+    }
+
+    #[test]
+    fn prev_and_next_shift_in_nulls() {
+        assert_eq!(q("1 2 3").prev(), Ok(q("0N 1 2")));
+        assert_eq!(q("1 2 3").next(), Ok(q("2 3 0N")));
+        assert_eq!(q("`a`b").prev(), Ok(q("``a")));
+        assert_eq!(q("2000.01.01 2000.01.02").next(), Ok(q("2000.01.02 0Nd")));
+    }
+
+    #[test]
+    fn differ_flags_changes() {
+        assert_eq!(q("1 1 2 2 3").differ(), Ok(q("10101b")));
+        assert_eq!(q("`a`a`b").differ(), Ok(q("101b")));
+        assert_eq!(Q::Null.differ(), Err(QError::Type));
+    }
+
+    #[test]
+    fn cross_pairs_every_item() {
+        assert_eq!(
+            q("1 2").cross(&q("`a`b")),
+            Ok(q("((1;`a);(1;`b);(2;`a);(2;`b))"))
+        );
+        assert_eq!(q("1 2").cross(&q("3 4")), Ok(q("(1 3;1 4;2 3;2 4)")));
+        assert_eq!(q("1").cross(&q("3 4")), Ok(q("(1 3;1 4)")));
+    }
+
+    #[test]
+    fn zip_with_pairs_items() {
+        let add = |x: Q, y: Q| match (x, y) {
+            (Q::Long(x), Q::Long(y)) => Q::Long(x + y),
+            _ => Q::Null,
+        };
+        assert_eq!(q("1 2 3").zip_with(&q("10 20 30"), add), Ok(q("11 22 33")));
+        assert_eq!(q("1 2 3").zip_with(&q("10"), add), Ok(q("11 12 13")));
+        assert_eq!(q("10").zip_with(&q("1 2"), add), Ok(q("11 12")));
+        assert_eq!(q("1 2 3").zip_with(&q("1 2"), add), Err(QError::Length));
+    }
+
+    #[test]
+    fn fills_carries_the_last_value_forward() {
+        assert_eq!(q("1 0N 0N 2 0N").fills(), Ok(q("1 1 1 2 2")));
+        assert_eq!(q("0N 3 0N").fills(), Ok(q("0N 3 3")));
+        assert_eq!(q("2000.01.01 0Nd").fills(), Ok(q("2000.01.01 2000.01.01")));
+        assert_eq!(q("`a``b").fills(), Err(QError::Type));
+    }
+
+    #[test]
+    fn sublist_clamps_to_the_list() {
+        let x = q("1 2 3 4 5");
+        assert_eq!(x.sublist(&q("2")), Ok(q("1 2")));
+        assert_eq!(x.sublist(&q("(1;2)")), Ok(q("2 3")));
+        assert_eq!(x.sublist(&q("-2")), Ok(q("4 5")));
+        assert_eq!(x.sublist(&q("9")), Ok(x.clone()));
+        assert_eq!(x.sublist(&q("(3;9)")), Ok(q("4 5")));
+        assert_eq!(q("\"abc\"").sublist(&q("(1;1)")), Ok(q("enlist \"b\"")));
+        assert_eq!(x.sublist(&q("1 2 3")), Err(QError::Length));
+    }
+
+    #[test]
+    fn flip_list_transposes() {
+        assert_eq!(q("(1 2 3;4 5 6)").flip_list(), Ok(q("(1 4;2 5;3 6)")));
+        assert_eq!(q("(1 2;`a)").flip_list(), Ok(q("((1;`a);(2;`a))")));
+        assert_eq!(q("(1 2;3 4 5)").flip_list(), Err(QError::Length));
+        assert_eq!(q("1 2").flip_list(), Err(QError::Type));
+    }
+
+    #[test]
+    fn take_and_drop_while() {
+        let less_than_3 = |x: &Q| matches!(x, Q::Long(n) if *n < 3);
+        assert_eq!(q("1 2 3 1").take_while(less_than_3), Ok(q("1 2")));
+        assert_eq!(q("1 2 3 1").drop_while(less_than_3), Ok(q("3 1")));
+        assert_eq!(q("1 2").take_while(less_than_3), Ok(q("1 2")));
+        assert_eq!(q("1 2").drop_while(less_than_3), Ok(q("`long$()")));
+        assert_eq!(q("5").take_while(less_than_3), Err(QError::Type));
+    }
+
+    #[test]
+    fn fold_reduces_left_to_right() {
+        let add = |x: Q, y: Q| match (x, y) {
+            (Q::Long(x), Q::Long(y)) => Q::Long(x + y),
+            _ => Q::Null,
+        };
+        assert_eq!(q("1 2 3 4").fold(None, add), Ok(q("10")));
+        assert_eq!(q("1 2 3 4").fold(Some(q("100")), add), Ok(q("110")));
+        let subtract = |x: Q, y: Q| match (x, y) {
+            (Q::Long(x), Q::Long(y)) => Q::Long(x - y),
+            _ => Q::Null,
+        };
+        assert_eq!(q("10 2 3").fold(None, subtract), Ok(q("5")));
+        assert_eq!(q("`long$()").fold(None, add), Ok(q("`long$()")));
+        assert_eq!(q("`long$()").fold(Some(q("7")), add), Ok(q("7")));
+    }
+
+    #[test]
+    fn scan_keeps_each_step() {
+        let add = |x: Q, y: Q| match (x, y) {
+            (Q::Long(x), Q::Long(y)) => Q::Long(x + y),
+            _ => Q::Null,
+        };
+        assert_eq!(q("1 2 3 4").scan(None, add), Ok(q("1 3 6 10")));
+        assert_eq!(
+            q("1 2 3 4").scan(Some(q("100")), add),
+            Ok(q("101 103 106 110"))
+        );
+        assert_eq!(q("`long$()").scan(Some(q("7")), add), Ok(q("`long$()")));
+    }
+
+    #[test]
+    fn to_list_promotes_to_a_general_list() {
+        assert_eq!(q("5").to_list(), Q::List(vec![Q::Long(5)]));
+        assert_eq!(q("1 2").to_list(), Q::List(vec![Q::Long(1), Q::Long(2)]));
+        let list = q("(1;`a)");
+        assert_eq!(list.to_list(), list);
+        let dict = q("`a`b!1 2");
+        assert_eq!(dict.to_list(), Q::List(vec![dict.clone()]));
+        assert_eq!(Q::collapse(q("1 2").to_list().items().unwrap()), q("1 2"));
+    }
+}
@@ -0,0 +1,784 @@
+// Elementwise arithmetic on numeric atoms and vectors
+use crate::qtype::null::Nullable;
+use crate::qtype::sort::cmp_float;
+use crate::qtype::{Q, QError};
+use ndarray::Array2;
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+
+// Applies `$f` to every element, keeping the numeric type of the argument
+macro_rules! map_numeric {
+    ($q:expr, $f:path) => {
+        match $q.without_attr_ref() {
+            Q::Short(x) => Ok(Q::Short($f(*x))),
+            Q::Int(x) => Ok(Q::Int($f(*x))),
+            Q::Long(x) => Ok(Q::Long($f(*x))),
+            Q::Real(x) => Ok(Q::Real($f(*x))),
+            Q::Float(x) => Ok(Q::Float($f(*x))),
+            Q::Shorts(v) => Ok(Q::Shorts(v.iter().map(|x| $f(*x)).collect())),
+            Q::Ints(v) => Ok(Q::Ints(v.iter().map(|x| $f(*x)).collect())),
+            Q::Longs(v) => Ok(Q::Longs(v.iter().map(|x| $f(*x)).collect())),
+            Q::Reals(v) => Ok(Q::Reals(v.iter().map(|x| $f(*x)).collect())),
+            Q::Floats(v) => Ok(Q::Floats(v.iter().map(|x| $f(*x)).collect())),
+            _ => Err(QError::Type),
+        }
+    };
+}
+
+// Applies `$f` to every element, collecting into the given atom/vector variants
+macro_rules! map_numeric_into {
+    ($q:expr, $f:path, $atom:ident, $vector:ident) => {
+        match $q.without_attr_ref() {
+            Q::Short(x) => Ok(Q::$atom($f(*x))),
+            Q::Int(x) => Ok(Q::$atom($f(*x))),
+            Q::Long(x) => Ok(Q::$atom($f(*x))),
+            Q::Real(x) => Ok(Q::$atom($f(*x))),
+            Q::Float(x) => Ok(Q::$atom($f(*x))),
+            Q::Shorts(v) => Ok(Q::$vector(v.iter().map(|x| $f(*x)).collect())),
+            Q::Ints(v) => Ok(Q::$vector(v.iter().map(|x| $f(*x)).collect())),
+            Q::Longs(v) => Ok(Q::$vector(v.iter().map(|x| $f(*x)).collect())),
+            Q::Reals(v) => Ok(Q::$vector(v.iter().map(|x| $f(*x)).collect())),
+            Q::Floats(v) => Ok(Q::$vector(v.iter().map(|x| $f(*x)).collect())),
+            _ => Err(QError::Type),
+        }
+    };
+}
+
+// Applies the binary `$f` elementwise to two arguments of the same numeric type, pairing
+// an atom with every item of a vector
+macro_rules! zip_numeric {
+    ($x:expr, $y:expr, $f:path) => {
+        zip_numeric!(
+            $x, $y, $f, Short / Shorts, Int / Ints, Long / Longs, Real / Reals, Float / Floats
+        )
+    };
+    ($x:expr, $y:expr, $f:path, $($atom:ident / $vector:ident),*) => {
+        match ($x.without_attr_ref(), $y.without_attr_ref()) {
+            $(
+                (Q::$atom(a), Q::$atom(b)) => Ok(Q::$atom($f(*a, *b))),
+                (Q::$vector(a), Q::$atom(b)) => {
+                    Ok(Q::$vector(a.iter().map(|a| $f(*a, *b)).collect()))
+                }
+                (Q::$atom(a), Q::$vector(b)) => {
+                    Ok(Q::$vector(b.iter().map(|b| $f(*a, *b)).collect()))
+                }
+                (Q::$vector(a), Q::$vector(b)) if a.len() == b.len() => {
+                    Ok(Q::$vector(a.iter().zip(b).map(|(a, b)| $f(*a, *b)).collect()))
+                }
+                (Q::$vector(_), Q::$vector(_)) => Err(QError::Length),
+            )*
+            _ => Err(QError::Type),
+        }
+    };
+}
+
+pub(crate) trait Numeric: Nullable {
+    const ZERO: Self;
+
+    fn q_abs(self) -> Self;
+    fn q_neg(self) -> Self;
+    fn q_signum(self) -> i32;
+    fn to_f64(self) -> f64;
+    fn q_cmp(self, y: Self) -> Ordering;
+    fn q_add(self, y: Self) -> Self;
+    fn q_sub(self, y: Self) -> Self;
+    fn q_mod(self, y: Self) -> Self;
+    fn q_div(self, y: Self) -> Self;
+}
+
+macro_rules! impl_numeric_int {
+    ($($t:ty),*) => {
+        $(
+            impl Numeric for $t {
+                const ZERO: Self = 0;
+
+                // wrapping keeps the null (MIN) as null, like q
+                fn q_abs(self) -> Self {
+                    self.wrapping_abs()
+                }
+
+                fn q_neg(self) -> Self {
+                    self.wrapping_neg()
+                }
+
+                fn q_signum(self) -> i32 {
+                    if self.is_null() { i32::NULL } else { self.signum() as i32 }
+                }
+
+                fn to_f64(self) -> f64 {
+                    if self.is_null() { f64::NULL } else { self as f64 }
+                }
+
+                // compared as integers, since large longs are not exact as floats
+                fn q_cmp(self, y: Self) -> Ordering {
+                    self.cmp(&y)
+                }
+
+                fn q_add(self, y: Self) -> Self {
+                    self.wrapping_add(y)
+                }
+
+                fn q_sub(self, y: Self) -> Self {
+                    self.wrapping_sub(y)
+                }
+
+                // q rounds the quotient down, so the remainder takes the sign of the divisor
+                fn q_mod(self, y: Self) -> Self {
+                    if self.is_null() || y.is_null() || y == 0 {
+                        return Self::NULL;
+                    }
+                    let r = self.wrapping_rem(y);
+                    if r != 0 && (r < 0) != (y < 0) { r + y } else { r }
+                }
+
+                fn q_div(self, y: Self) -> Self {
+                    if self.is_null() || y.is_null() || y == 0 {
+                        return Self::NULL;
+                    }
+                    self.wrapping_sub(self.q_mod(y)).wrapping_div(y)
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! impl_numeric_float {
+    ($($t:ty),*) => {
+        $(
+            impl Numeric for $t {
+                const ZERO: Self = 0.0;
+
+                fn q_abs(self) -> Self {
+                    self.abs()
+                }
+
+                fn q_neg(self) -> Self {
+                    -self
+                }
+
+                fn q_signum(self) -> i32 {
+                    if self.is_null() {
+                        i32::NULL
+                    } else if self == 0.0 {
+                        0
+                    } else {
+                        self.signum() as i32
+                    }
+                }
+
+                fn to_f64(self) -> f64 {
+                    self as f64
+                }
+
+                fn q_cmp(self, y: Self) -> Ordering {
+                    cmp_float(self as f64, y as f64)
+                }
+
+                fn q_add(self, y: Self) -> Self {
+                    self + y
+                }
+
+                fn q_sub(self, y: Self) -> Self {
+                    self - y
+                }
+
+                fn q_mod(self, y: Self) -> Self {
+                    self - y * (self / y).floor()
+                }
+
+                fn q_div(self, y: Self) -> Self {
+                    (self / y).floor()
+                }
+            }
+        )*
+    };
+}
+
+impl_numeric_int!(i16, i32, i64);
+impl_numeric_float!(f32, f64);
+
+fn reciprocal<T: Numeric>(x: T) -> f64 {
+    1.0 / x.to_f64()
+}
+
+impl Q {
+    /// q's `abs`, e.g. `abs -1 2 -3` is `1 2 3`
+    pub fn abs(&self) -> Result<Q, QError> {
+        map_numeric!(self, Numeric::q_abs)
+    }
+
+    /// q's `neg`
+    pub fn neg(&self) -> Result<Q, QError> {
+        map_numeric!(self, Numeric::q_neg)
+    }
+
+    /// q's `signum`, which always returns ints, e.g. `signum -5 0 5` is `-1 0 1i`
+    pub fn signum(&self) -> Result<Q, QError> {
+        map_numeric_into!(self, Numeric::q_signum, Int, Ints)
+    }
+
+    /// q's `reciprocal`, which always returns floats
+    pub fn reciprocal(&self) -> Result<Q, QError> {
+        map_numeric_into!(self, reciprocal, Float, Floats)
+    }
+
+    /// q's `x mod y`: the remainder of dividing `self` by `other` rounded down, which takes
+    /// the sign of the divisor, e.g. `-7 mod 3` is `2`. An integer divisor of zero gives null.
+    pub fn q_mod(&self, other: &Q) -> Result<Q, QError> {
+        zip_numeric!(self, other, Numeric::q_mod)
+    }
+
+    /// q's `x div y`: the quotient of `self` by `other` rounded down, e.g. `-7 div 3` is `-3`.
+    /// An integer divisor of zero gives null.
+    pub fn q_div(&self, other: &Q) -> Result<Q, QError> {
+        zip_numeric!(self, other, Numeric::q_div)
+    }
+
+    /// q's `ratios`: each item divided by its predecessor, as floats, with the first item
+    /// kept as is, e.g. `ratios 1 2 4` is `1 2 2f`
+    pub fn ratios(&self) -> Result<Q, QError> {
+        let ratios = match self.without_attr_ref() {
+            Q::Shorts(v) => ratios(v),
+            Q::Ints(v) => ratios(v),
+            Q::Longs(v) => ratios(v),
+            Q::Reals(v) => ratios(v),
+            Q::Floats(v) => ratios(v),
+            _ => return Err(QError::Type),
+        };
+        Ok(Q::Floats(ratios))
+    }
+
+    /// q's `n mavg x`: the average of each item and up to `n-1` items before it, as floats.
+    /// Nulls are left out of the average, and a window of only nulls gives null.
+    pub fn mavg(&self, window: usize) -> Result<Q, QError> {
+        fn mavg<T: Numeric>(v: &[T], n: usize) -> Vec<f64> {
+            let v: Vec<f64> = v.iter().map(|x| x.to_f64()).collect();
+            moving_sums(&v, n)
+                .map(|(total, count)| total / count as f64)
+                .collect()
+        }
+        if window == 0 {
+            return Err(QError::Domain);
+        }
+        let averages = match self.without_attr_ref() {
+            Q::Shorts(v) => mavg(v, window),
+            Q::Ints(v) => mavg(v, window),
+            Q::Longs(v) => mavg(v, window),
+            Q::Reals(v) => mavg(v, window),
+            Q::Floats(v) => mavg(v, window),
+            _ => return Err(QError::Type),
+        };
+        Ok(Q::Floats(averages))
+    }
+
+    /// q's `n msum x`: the sum of each item and up to `n-1` items before it, keeping the type
+    /// of `x`. Nulls count as zero.
+    pub fn msum(&self, window: usize) -> Result<Q, QError> {
+        fn msum<T: Numeric>(v: &[T], n: usize) -> Vec<T> {
+            moving_sums(v, n).map(|(total, _)| total).collect()
+        }
+        if window == 0 {
+            return Err(QError::Domain);
+        }
+        Ok(match self.without_attr_ref() {
+            Q::Shorts(v) => Q::Shorts(msum(v, window)),
+            Q::Ints(v) => Q::Ints(msum(v, window)),
+            Q::Longs(v) => Q::Longs(msum(v, window)),
+            Q::Reals(v) => Q::Reals(msum(v, window)),
+            Q::Floats(v) => Q::Floats(msum(v, window)),
+            _ => return Err(QError::Type),
+        })
+    }
+
+    /// q's `alpha ema x`: the exponential moving average of `self` as floats, seeded with its
+    /// first item, so each result is `alpha*x + (1-alpha)*previous`. A null carries the
+    /// previous result forward, and leading nulls stay null.
+    pub fn ema(&self, alpha: f64) -> Result<Q, QError> {
+        fn ema<T: Numeric>(v: &[T], alpha: f64) -> Vec<f64> {
+            let mut previous = f64::NULL;
+            v.iter()
+                .map(|x| {
+                    let x = x.to_f64();
+                    if previous.is_null() {
+                        previous = x;
+                    } else if !x.is_null() {
+                        previous = alpha * x + (1.0 - alpha) * previous;
+                    }
+                    previous
+                })
+                .collect()
+        }
+        if !(0.0..=1.0).contains(&alpha) {
+            return Err(QError::Domain);
+        }
+        let averages = match self.without_attr_ref() {
+            Q::Shorts(v) => ema(v, alpha),
+            Q::Ints(v) => ema(v, alpha),
+            Q::Longs(v) => ema(v, alpha),
+            Q::Reals(v) => ema(v, alpha),
+            Q::Floats(v) => ema(v, alpha),
+            _ => return Err(QError::Type),
+        };
+        Ok(Q::Floats(averages))
+    }
+
+    /// Applies `f` to the underlying int of each item of a temporal atom or vector, e.g. days
+    /// since 2000.01.01 for dates, keeping the type. Results are clamped into the range of the
+    /// type, and nulls and infinities are left as they are.
+    pub fn map_temporal<F: Fn(i64) -> i64>(&self, f: F) -> Result<Q, QError> {
+        macro_rules! map {
+            ($($atom:ident / $vector:ident),*) => {
+                match self.without_attr_ref() {
+                    $(
+                        Q::$atom(x) => Q::$atom(x.map_raw(&f)),
+                        Q::$vector(v) => Q::$vector(v.iter().map(|x| x.map_raw(&f)).collect()),
+                    )*
+                    _ => return Err(QError::Type),
+                }
+            };
+        }
+        Ok(map!(
+            Timestamp / Timestamps,
+            Month / Months,
+            Date / Dates,
+            Timespan / Timespans,
+            Minute / Minutes,
+            Second / Seconds
+        ))
+    }
+
+    /// q's `n mmax x`: the maximum of each item and up to `n-1` items before it, ignoring nulls
+    pub fn mmax(&self, window: usize) -> Result<Q, QError> {
+        self.mextreme(window, Ordering::Greater)
+    }
+
+    /// q's `n mmin x`: the minimum of each item and up to `n-1` items before it, ignoring nulls
+    pub fn mmin(&self, window: usize) -> Result<Q, QError> {
+        self.mextreme(window, Ordering::Less)
+    }
+
+    /// q's `x mmu y`: the matrix product of two float matrices, each a general list of
+    /// equal-length float vectors (its rows)
+    pub fn mmu(&self, other: &Q) -> Result<Q, QError> {
+        let (lhs, rhs) = (matrix(self)?, matrix(other)?);
+        if lhs.ncols() != rhs.nrows() {
+            return Err(QError::Length);
+        }
+        let product = lhs.dot(&rhs);
+        Ok(Q::List(
+            product
+                .rows()
+                .into_iter()
+                .map(|row| Q::Floats(row.to_vec()))
+                .collect(),
+        ))
+    }
+
+    /// q's `weights wavg x`: the average of `self` weighted by `weights`, as a float. Pairs
+    /// where either is null are left out, and with none left the result is null.
+    pub fn wavg(&self, weights: &Q) -> Result<Q, QError> {
+        let (total, weight) =
+            float_pairs(self, weights)?.fold((0.0, 0.0), |(t, w), (x, wx)| (t + x * wx, w + wx));
+        Ok(Q::Float(total / weight))
+    }
+
+    /// q's `weights wsum x`: the sum of `self` times `weights`, as a float. Pairs where
+    /// either is null are left out.
+    pub fn wsum(&self, weights: &Q) -> Result<Q, QError> {
+        Ok(Q::Float(
+            float_pairs(self, weights)?.map(|(x, w)| x * w).sum(),
+        ))
+    }
+
+    /// q's `x cov y`: the population covariance of `self` and `other`, as a float. Pairs
+    /// where either is null are left out, and with none left the result is null.
+    pub fn cov(&self, other: &Q) -> Result<Q, QError> {
+        let pairs: Vec<(f64, f64)> = float_pairs(self, other)?.collect();
+        Ok(Q::Float(covariance(&pairs)))
+    }
+
+    /// q's `x cor y`: the correlation of `self` and `other`, their covariance over the
+    /// product of their standard deviations. Pairs where either is null are left out.
+    pub fn cor(&self, other: &Q) -> Result<Q, QError> {
+        let pairs: Vec<(f64, f64)> = float_pairs(self, other)?.collect();
+        let xs: Vec<(f64, f64)> = pairs.iter().map(|&(x, _)| (x, x)).collect();
+        let ys: Vec<(f64, f64)> = pairs.iter().map(|&(_, y)| (y, y)).collect();
+        Ok(Q::Float(
+            covariance(&pairs) / (covariance(&xs) * covariance(&ys)).sqrt(),
+        ))
+    }
+}
+
+impl Q {
+    /// q's `base sv digits`: the number whose digits in `base` are `self`, most significant
+    /// first, e.g. `2 sv 1 0 1b` is `5` and `256 sv 0x0102` is `258`
+    pub fn base_sv(&self, base: i64) -> Result<Q, QError> {
+        if base < 2 {
+            return Err(QError::Domain);
+        }
+        let digits: Vec<i64> = match self.without_attr_ref() {
+            Q::Booleans(v) => v.iter().map(|&b| b as i64).collect(),
+            Q::Bytes(v) => v.iter().map(|&b| b as i64).collect(),
+            Q::Shorts(v) => v.iter().map(|&x| x as i64).collect(),
+            Q::Ints(v) => v.iter().map(|&x| x as i64).collect(),
+            Q::Longs(v) => v.clone(),
+            _ => return Err(QError::Type),
+        };
+        Ok(Q::Long(
+            digits
+                .iter()
+                .fold(0i64, |n, &d| n.wrapping_mul(base).wrapping_add(d)),
+        ))
+    }
+
+    /// q's `base vs n`: the digits of the non-negative integer `self` in `base`, most
+    /// significant first, e.g. `2 vs 5` is `1 0 1`. For a vector, the result has one row per
+    /// digit position, as many as the largest item needs.
+    pub fn base_vs(&self, base: i64) -> Result<Q, QError> {
+        fn digits(mut n: i64, base: i64, count: usize) -> Vec<i64> {
+            let mut digits = vec![0; count];
+            for digit in digits.iter_mut().rev() {
+                *digit = n % base;
+                n /= base;
+            }
+            digits
+        }
+        fn width(mut n: i64, base: i64) -> usize {
+            let mut count = 0;
+            while n > 0 {
+                n /= base;
+                count += 1;
+            }
+            count
+        }
+        if base < 2 {
+            return Err(QError::Domain);
+        }
+        let (numbers, atom): (Vec<i64>, bool) = match self.without_attr_ref() {
+            Q::Short(x) => (vec![*x as i64], true),
+            Q::Int(x) => (vec![*x as i64], true),
+            Q::Long(x) => (vec![*x], true),
+            Q::Shorts(v) => (v.iter().map(|&x| x as i64).collect(), false),
+            Q::Ints(v) => (v.iter().map(|&x| x as i64).collect(), false),
+            Q::Longs(v) => (v.clone(), false),
+            _ => return Err(QError::Type),
+        };
+        if numbers.iter().any(|&n| n < 0) {
+            return Err(QError::Domain);
+        }
+        let count = numbers.iter().map(|&n| width(n, base)).max().unwrap_or(0);
+        let rows: Vec<Vec<i64>> = numbers.iter().map(|&n| digits(n, base, count)).collect();
+        if atom {
+            return Ok(Q::Longs(rows.into_iter().next().unwrap()));
+        }
+        Ok(Q::List(
+            (0..count)
+                .map(|k| Q::Longs(rows.iter().map(|row| row[k]).collect()))
+                .collect(),
+        ))
+    }
+}
+
+/// The items of two equal-length numeric vectors as float pairs, without those holding a null
+fn float_pairs(x: &Q, y: &Q) -> Result<impl Iterator<Item = (f64, f64)>, QError> {
+    fn floats(q: &Q) -> Result<Vec<f64>, QError> {
+        fn floats<T: Numeric>(v: &[T]) -> Vec<f64> {
+            v.iter().map(|x| x.to_f64()).collect()
+        }
+        Ok(match q.without_attr_ref() {
+            Q::Shorts(v) => floats(v),
+            Q::Ints(v) => floats(v),
+            Q::Longs(v) => floats(v),
+            Q::Reals(v) => floats(v),
+            Q::Floats(v) => floats(v),
+            _ => return Err(QError::Type),
+        })
+    }
+    let (x, y) = (floats(x)?, floats(y)?);
+    if x.len() != y.len() {
+        return Err(QError::Length);
+    }
+    Ok(x.into_iter()
+        .zip(y)
+        .filter(|(x, y)| !x.is_null() && !y.is_null()))
+}
+
+/// The population covariance of `pairs`, NaN (null) when there are none
+fn covariance(pairs: &[(f64, f64)]) -> f64 {
+    let n = pairs.len() as f64;
+    let (mx, my) = pairs
+        .iter()
+        .fold((0.0, 0.0), |(sx, sy), (x, y)| (sx + x / n, sy + y / n));
+    pairs.iter().map(|(x, y)| (x - mx) * (y - my)).sum::<f64>() / n
+}
+
+fn ratios<T: Numeric>(v: &[T]) -> Vec<f64> {
+    let v: Vec<f64> = v.iter().map(|x| x.to_f64()).collect();
+    (0..v.len())
+        .map(|i| if i == 0 { v[0] } else { v[i] / v[i - 1] })
+        .collect()
+}
+
+impl Q {
+    // Helper methods
+    fn mextreme(&self, window: usize, wanted: Ordering) -> Result<Q, QError> {
+        // a deque of the indices of the window's candidates, whose values run from the
+        // extreme down; each index is pushed and popped once
+        fn mextreme<T: Numeric>(v: &[T], n: usize, wanted: Ordering) -> Vec<T> {
+            let mut candidates: VecDeque<usize> = VecDeque::new();
+            (0..v.len())
+                .map(|i| {
+                    if !v[i].is_null() {
+                        while candidates
+                            .back()
+                            .is_some_and(|&j| v[i].q_cmp(v[j]) == wanted)
+                        {
+                            candidates.pop_back();
+                        }
+                        candidates.push_back(i);
+                    }
+                    if candidates.front().is_some_and(|&j| j + n <= i) {
+                        candidates.pop_front();
+                    }
+                    candidates.front().map_or(T::NULL, |&j| v[j])
+                })
+                .collect()
+        }
+        if window == 0 {
+            return Err(QError::Domain);
+        }
+        Ok(match self.without_attr_ref() {
+            Q::Shorts(v) => Q::Shorts(mextreme(v, window, wanted)),
+            Q::Ints(v) => Q::Ints(mextreme(v, window, wanted)),
+            Q::Longs(v) => Q::Longs(mextreme(v, window, wanted)),
+            Q::Reals(v) => Q::Reals(mextreme(v, window, wanted)),
+            Q::Floats(v) => Q::Floats(mextreme(v, window, wanted)),
+            _ => return Err(QError::Type),
+        })
+    }
+}
+
+/// The sum of the non-null items of `v`, zero when there are none
+pub(crate) fn sum<T: Numeric>(v: &[T]) -> T {
+    v.iter()
+        .filter(|x| !x.is_null())
+        .fold(T::ZERO, |a, b| a.q_add(*b))
+}
+
+/// The average of the non-null items of `v`, null when there are none
+pub(crate) fn avg<T: Numeric>(v: &[T]) -> f64 {
+    let present: Vec<f64> = v
+        .iter()
+        .filter(|x| !x.is_null())
+        .map(|x| x.to_f64())
+        .collect();
+    present.iter().sum::<f64>() / present.len() as f64
+}
+
+/// The greatest (`Greater`) or least (`Less`) non-null item of `v`, null when there are none
+pub(crate) fn extreme<T: Numeric>(v: &[T], wanted: Ordering) -> T {
+    v.iter()
+        .filter(|x| !x.is_null())
+        .copied()
+        .reduce(|a, b| if b.q_cmp(a) == wanted { b } else { a })
+        .unwrap_or(T::NULL)
+}
+
+/// The sum and count of the non-null items in the window of up to `n` items ending at each
+/// item, kept running by adding the item that enters and subtracting the one that leaves, as
+/// q's `msum` does
+fn moving_sums<T: Numeric>(v: &[T], n: usize) -> impl Iterator<Item = (T, usize)> {
+    let (mut total, mut count) = (T::ZERO, 0);
+    (0..v.len()).map(move |i| {
+        if !v[i].is_null() {
+            total = total.q_add(v[i]);
+            count += 1;
+        }
+        if let Some(&left) = i.checked_sub(n).map(|j| &v[j])
+            && !left.is_null()
+        {
+            total = total.q_sub(left);
+            count -= 1;
+        }
+        (total, count)
+    })
+}
+
+/// The rows of a float matrix, which must not be ragged
+fn matrix(q: &Q) -> Result<Array2<f64>, QError> {
+    let Q::List(rows) = q.without_attr_ref() else {
+        return Err(QError::Type);
+    };
+    let ncols = rows.first().map_or(0, Q::len);
+    let mut cells = Vec::with_capacity(rows.len() * ncols);
+    for row in rows {
+        let Q::Floats(row) = row.without_attr_ref() else {
+            return Err(QError::Type);
+        };
+        if row.len() != ncols {
+            return Err(QError::Length);
+        }
+        cells.extend_from_slice(row);
+    }
+    Ok(Array2::from_shape_vec((rows.len(), ncols), cells).unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::qtype::attr::Attr;
+
+    fn q(input: &str) -> Q {
+        crate::parse_one(input).unwrap()
+    }
+
+    #[test]
+    fn elementwise_helpers() {
+        assert_eq!(q("-1 2 -3").abs(), Ok(q("1 2 3")));
+        assert_eq!(q("-5 0 5").signum(), Ok(q("-1 0 1i")));
+        assert_eq!(q("1 0N -3").neg(), Ok(q("-1 0N 3")));
+        assert_eq!(q("-2").abs(), Ok(q("2")));
+        // NaN is not equal to itself, so nulls are compared by how they print
+        assert_eq!(q("2 4 0N").reciprocal().unwrap().to_string(), "0.5 0.25 0n");
+        assert_eq!(q("`a").abs(), Err(QError::Type));
+        let sorted = Q::Attributed(Attr::Sorted, Box::new(q("-1 2 3")));
+        assert_eq!(sorted.abs(), Ok(q("1 2 3")));
+        assert_eq!(sorted.signum(), Ok(q("-1 1 1i")));
+    }
+
+    #[test]
+    fn mmu_multiplies_float_matrices() {
+        let a = q("(1 2 3f;4 5 6f)");
+        let b = q("(7 8f;9 10f;11 12f)");
+        assert_eq!(a.mmu(&b), Ok(q("(58 64f;139 154f)")));
+        assert_eq!(a.mmu(&a), Err(QError::Length));
+        assert_eq!(q("(1 2f;3 4 5f)").mmu(&b), Err(QError::Length));
+        assert_eq!(q("(1 2;3 4)").mmu(&q("(1 2;3 4)")), Err(QError::Type));
+        let sorted = Q::List(vec![
+            Q::Attributed(Attr::Sorted, Box::new(q("1 2f"))),
+            Q::Attributed(Attr::Sorted, Box::new(q("3 4f"))),
+        ]);
+        assert_eq!(sorted.mmu(&q("(1 0f;0 1f)")), Ok(q("(1 2f;3 4f)")));
+    }
+
+    #[test]
+    fn ratios_divide_by_the_predecessor() {
+        assert_eq!(q("1 2 4").ratios(), Ok(q("1 2 2f")));
+        assert_eq!(q("4 2").ratios(), Ok(q("4 0.5")));
+        assert_eq!(Q::Longs(vec![]).ratios(), Ok(Q::Floats(vec![])));
+        let sorted = Q::Attributed(Attr::Sorted, Box::new(q("1 2 4")));
+        assert_eq!(sorted.ratios(), Ok(q("1 2 2f")));
+    }
+
+    #[test]
+    fn moving_window_aggregations() {
+        assert_eq!(q("1 2 3 4 5").mavg(3), Ok(q("1 1.5 2 3 4")));
+        assert_eq!(q("1 2 3 4 5").msum(3), Ok(q("1 3 6 9 12")));
+        assert_eq!(q("3 1 2 4").mmax(2), Ok(q("3 3 2 4")));
+        assert_eq!(q("3 1 2 4").mmin(3), Ok(q("3 1 1 1")));
+        // nulls are skipped within a window
+        assert_eq!(q("1 0N 3").msum(2), Ok(q("1 1 3")));
+        assert_eq!(q("0N 0N 2").mmax(2), Ok(q("0N 0N 2")));
+        assert_eq!(q("0N 0N 2").mavg(2).unwrap().to_string(), "0n 0n 2");
+        assert_eq!(q("5 4 3 2 1").mmax(2), Ok(q("5 5 4 3 2")));
+        assert_eq!(q("1 2 3").mmin(5), Ok(q("1 1 1")));
+        // windows are kept running, so a long window over many items stays linear
+        let v = Q::Longs((0..40_000).collect());
+        assert_eq!(v.msum(40_000).unwrap().len(), 40_000);
+        assert_eq!(
+            v.mmin(20_000),
+            Ok(Q::Longs([0; 20_000].into_iter().chain(1..20_001).collect()))
+        );
+        let sorted = Q::Attributed(Attr::Sorted, Box::new(q("1 2 3 4 5")));
+        assert_eq!(sorted.mavg(3), Ok(q("1 1.5 2 3 4")));
+        assert_eq!(sorted.msum(3), Ok(q("1 3 6 9 12")));
+        assert_eq!(sorted.mmin(2), Ok(q("1 1 2 3 4")));
+    }
+
+    #[test]
+    fn mod_and_div_round_down() {
+        // Rust's `%` and `/` give -1 and -2 here
+        assert_eq!(q("-7").q_mod(&q("3")), Ok(q("2")));
+        assert_eq!(q("-7").q_div(&q("3")), Ok(q("-3")));
+        assert_eq!(q("7").q_mod(&q("-3")), Ok(q("-2")));
+        assert_eq!(q("7").q_div(&q("-3")), Ok(q("-3")));
+        assert_eq!(q("-7 7 8").q_mod(&q("3")), Ok(q("2 1 2")));
+        assert_eq!(q("7").q_div(&q("0")), Ok(q("0N")));
+        let sorted = Q::Attributed(Attr::Sorted, Box::new(q("-7 7 8")));
+        assert_eq!(sorted.q_mod(&q("3")), Ok(q("2 1 2")));
+        assert_eq!(q("7").q_div(&sorted), Ok(q("-1 1 0")));
+    }
+
+    #[test]
+    fn base_conversions() {
+        assert_eq!(q("101b").base_sv(2), Ok(q("5")));
+        assert_eq!(q("0x0102").base_sv(256), Ok(q("258")));
+        assert_eq!(q("5").base_vs(2), Ok(q("1 0 1")));
+        assert_eq!(q("258").base_vs(256), Ok(q("1 2")));
+        assert_eq!(q("1 5").base_vs(2), Ok(q("(0 1;0 0;1 1)")));
+        assert_eq!(q("-5").base_vs(2), Err(QError::Domain));
+        assert_eq!(q("101b").base_sv(1), Err(QError::Domain));
+    }
+
+    #[test]
+    fn ema_matches_q() {
+        assert_eq!(q("1 2 3 4 5").ema(0.5), Ok(q("1 1.5 2.25 3.125 4.0625")));
+        assert_eq!(
+            q("0N 2 0N 4").ema(0.5).unwrap().to_string(),
+            q("0n 2 2 3").to_string()
+        );
+        assert_eq!(q("1 2").ema(1.5), Err(QError::Domain));
+        assert_eq!(q("`a`b").ema(0.5), Err(QError::Type));
+    }
+
+    #[test]
+    fn map_temporal_shifts_underlying_ints() {
+        assert_eq!(
+            q("2024.01.01 2024.02.29 0Nd").map_temporal(|d| d + 1),
+            Ok(q("2024.01.02 2024.03.01 0Nd"))
+        );
+        let hour = 3_600_000_000_000;
+        assert_eq!(
+            q("2024.01.01D23:00 2024.01.02D00:30").map_temporal(|ns| ns + hour),
+            Ok(q("2024.01.02D00:00 2024.01.02D01:30"))
+        );
+        assert_eq!(q("9999.12.31").map_temporal(|d| d + 1), Ok(q("9999.12.31")));
+        assert_eq!(q("0Wd").map_temporal(|d| d - 1), Ok(q("0Wd")));
+        assert_eq!(q("1 2").map_temporal(|x| x), Err(QError::Type));
+    }
+
+    #[test]
+    fn weighted_average_and_sum() {
+        assert_eq!(q("10 20").wavg(&q("2 3")), Ok(Q::Float(16.0)));
+        assert_eq!(q("10 20").wsum(&q("2 3")), Ok(Q::Float(80.0)));
+        assert_eq!(q("10 0N 20").wavg(&q("2 5 3")), Ok(Q::Float(16.0)));
+        assert_eq!(q("10 20").wsum(&q("2 0N")), Ok(Q::Float(20.0)));
+        assert_eq!(q("0N 0N").wavg(&q("1 2")).unwrap().to_string(), "0n");
+        assert_eq!(q("10 20").wavg(&q("1 2 3")), Err(QError::Length));
+        assert_eq!(q("10 20").wsum(&q("1 2 3")), Err(QError::Length));
+        assert_eq!(q("10 20").wavg(&q("`a`b")), Err(QError::Type));
+    }
+
+    #[test]
+    fn covariance_and_correlation() {
+        // means 2.5 and 5.25; deviation products 4.875 .625 .375 5.625 over 4
+        assert_eq!(q("1 2 3 4").cov(&q("2 4 6 9")), Ok(Q::Float(2.875)));
+        assert_eq!(q("1 2 3 4").cov(&q("1 2 3 4")), Ok(Q::Float(1.25)));
+        assert_eq!(q("1 2 0N 3 4").cov(&q("2 4 5 6 9")), Ok(Q::Float(2.875)));
+        let Ok(Q::Float(r)) = q("1 2 3").cor(&q("2 4 6")) else {
+            panic!("cor should be a float");
+        };
+        assert!((r - 1.0).abs() < 1e-12);
+        let Ok(Q::Float(r)) = q("1 2 3").cor(&q("3 2 1")) else {
+            panic!("cor should be a float");
+        };
+        assert!((r + 1.0).abs() < 1e-12);
+        // 2.875 / sqrt(1.25 * 6.6875)
+        let Ok(Q::Float(r)) = q("1 2 3 4").cor(&q("2 4 6 9")) else {
+            panic!("cor should be a float");
+        };
+        assert!((r - 0.994_376_7).abs() < 1e-6);
+        assert_eq!(q("0N 1").cov(&q("1 0N")).unwrap().to_string(), "0n");
+        assert_eq!(q("1 2").cov(&q("1 2 3")), Err(QError::Length));
+    }
+}
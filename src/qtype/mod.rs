@@ -1,9 +1,31 @@
+pub mod aggregate;
+pub mod attr;
+pub mod cast;
 pub mod chrono;
+pub mod csv;
+pub mod dict;
+pub mod display;
+pub mod enums;
+pub mod error;
+pub mod ipc;
+pub mod list;
+pub mod math;
+pub mod null;
+pub mod partition;
+pub mod sort;
+pub mod store;
+pub mod strings;
 pub mod symbol;
+pub mod table;
+pub mod validate;
 
+use crate::qtype::attr::Attr;
 use crate::qtype::chrono::{Date, Minute, Month, Second, Timespan, Timestamp};
+use crate::qtype::null::Nullable;
 use crate::qtype::symbol::Symbol;
 
+pub use crate::qtype::error::QError;
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Q {
     // atom
@@ -23,4 +45,237 @@ pub enum Q {
     Timespan(Timespan),
     Minute(Minute),
     Second(Second),
+
+    // vector
+    Booleans(Vec<bool>),
+    Guids(Vec<uuid::Uuid>),
+    Bytes(Vec<u8>),
+    Shorts(Vec<i16>),
+    Ints(Vec<i32>),
+    Longs(Vec<i64>),
+    Reals(Vec<f32>),
+    Floats(Vec<f64>),
+    String(Vec<u8>),
+    Symbols(Vec<Symbol>),
+    Timestamps(Vec<Timestamp>),
+    Months(Vec<Month>),
+    Dates(Vec<Date>),
+    Timespans(Vec<Timespan>),
+    Minutes(Vec<Minute>),
+    Seconds(Vec<Second>),
+
+    // general list
+    List(Vec<Q>),
+
+    // keys!values
+    Dict(Box<Q>, Box<Q>),
+
+    // flip columns!values
+    Table(Vec<Symbol>, Vec<Q>),
+
+    // `domain$symbols, with indices a long atom or vector
+    Enum { domain: Symbol, indices: Box<Q> },
+
+    // attr#list
+    Attributed(Attr, Box<Q>),
+
+    // generic null, i.e. `::`
+    Null,
+}
+
+impl Q {
+    /// q's `count`: the number of items of a list, the number of keys of a dictionary, or 1
+    /// for an atom
+    pub fn len(&self) -> usize {
+        match self {
+            Q::Booleans(v) => v.len(),
+            Q::Guids(v) => v.len(),
+            Q::Bytes(v) => v.len(),
+            Q::Shorts(v) => v.len(),
+            Q::Ints(v) => v.len(),
+            Q::Longs(v) => v.len(),
+            Q::Reals(v) => v.len(),
+            Q::Floats(v) => v.len(),
+            Q::String(v) => v.len(),
+            Q::Symbols(v) => v.len(),
+            Q::Timestamps(v) => v.len(),
+            Q::Months(v) => v.len(),
+            Q::Dates(v) => v.len(),
+            Q::Timespans(v) => v.len(),
+            Q::Minutes(v) => v.len(),
+            Q::Seconds(v) => v.len(),
+            Q::List(v) => v.len(),
+            Q::Dict(keys, _) => keys.len(),
+            Q::Table(_, values) => values.first().map_or(0, Q::len),
+            Q::Enum { indices, .. } => indices.len(),
+            Q::Attributed(_, list) => list.len(),
+            _ => 1,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn is_atom(&self) -> bool {
+        matches!(
+            self,
+            Q::Boolean(_)
+                | Q::Guid(_)
+                | Q::Byte(_)
+                | Q::Short(_)
+                | Q::Int(_)
+                | Q::Long(_)
+                | Q::Real(_)
+                | Q::Float(_)
+                | Q::Char(_)
+                | Q::Symbol(_)
+                | Q::Timestamp(_)
+                | Q::Month(_)
+                | Q::Date(_)
+                | Q::Timespan(_)
+                | Q::Minute(_)
+                | Q::Second(_)
+        ) || matches!(self, Q::Enum { indices, .. } if indices.is_atom())
+    }
+
+    /// q's `type`: negative for atoms, positive for lists, 0 for a general list
+    pub fn type_id(&self) -> i8 {
+        match self {
+            Q::Boolean(_) => -1,
+            Q::Guid(_) => -2,
+            Q::Byte(_) => -4,
+            Q::Short(_) => -5,
+            Q::Int(_) => -6,
+            Q::Long(_) => -7,
+            Q::Real(_) => -8,
+            Q::Float(_) => -9,
+            Q::Char(_) => -10,
+            Q::Symbol(_) => -11,
+            Q::Timestamp(_) => -12,
+            Q::Month(_) => -13,
+            Q::Date(_) => -14,
+            Q::Timespan(_) => -16,
+            Q::Minute(_) => -17,
+            Q::Second(_) => -18,
+            Q::Booleans(_) => 1,
+            Q::Guids(_) => 2,
+            Q::Bytes(_) => 4,
+            Q::Shorts(_) => 5,
+            Q::Ints(_) => 6,
+            Q::Longs(_) => 7,
+            Q::Reals(_) => 8,
+            Q::Floats(_) => 9,
+            Q::String(_) => 10,
+            Q::Symbols(_) => 11,
+            Q::Timestamps(_) => 12,
+            Q::Months(_) => 13,
+            Q::Dates(_) => 14,
+            Q::Timespans(_) => 16,
+            Q::Minutes(_) => 17,
+            Q::Seconds(_) => 18,
+            Q::List(_) => 0,
+            Q::Enum { indices, .. } if indices.is_atom() => -20,
+            Q::Enum { .. } => 20,
+            Q::Table(..) => 98,
+            Q::Dict(..) => 99,
+            Q::Attributed(_, list) => list.type_id(),
+            Q::Null => 101,
+        }
+    }
+
+    /// Whether `self` and `other` have exactly the same type id
+    pub fn same_type(&self, other: &Q) -> bool {
+        self.type_id() == other.type_id()
+    }
+
+    /// Whether `self` and `other` are both numeric (boolean through float), so arithmetic
+    /// between them promotes to a common type
+    pub fn numeric_compatible(&self, other: &Q) -> bool {
+        let numeric = |q: &Q| matches!(q.type_id().abs(), 1 | 4..=9);
+        numeric(self) && numeric(other)
+    }
+
+    /// The i-th item of a list, as an atom for simple vectors
+    pub fn at(&self, i: usize) -> Option<Q> {
+        macro_rules! item {
+            ($($vector:ident => $atom:ident),*) => {
+                match self {
+                    $(Q::$vector(v) => v.get(i).map(|x| Q::$atom(*x)),)*
+                    Q::List(v) => v.get(i).cloned(),
+                    Q::Enum { domain, indices } => Q::enum_at(*domain, indices, i),
+                    Q::Attributed(_, list) => list.at(i),
+                    _ => None,
+                }
+            };
+        }
+        item!(
+            Booleans => Boolean,
+            Guids => Guid,
+            Bytes => Byte,
+            Shorts => Short,
+            Ints => Int,
+            Longs => Long,
+            Reals => Real,
+            Floats => Float,
+            String => Char,
+            Symbols => Symbol,
+            Timestamps => Timestamp,
+            Months => Month,
+            Dates => Date,
+            Timespans => Timespan,
+            Minutes => Minute,
+            Seconds => Second
+        )
+    }
+
+    /// The typed null atom matching the items of `self`, e.g. `0Nj` for longs. A general list
+    /// has no typed null and gives the empty list.
+    pub fn null_item(&self) -> Q {
+        match self {
+            Q::Boolean(_) | Q::Booleans(_) => Q::Boolean(false),
+            Q::Guid(_) | Q::Guids(_) => Q::Guid(uuid::Uuid::nil()),
+            Q::Byte(_) | Q::Bytes(_) => Q::Byte(0),
+            Q::Short(_) | Q::Shorts(_) => Q::Short(i16::NULL),
+            Q::Int(_) | Q::Ints(_) => Q::Int(i32::NULL),
+            Q::Long(_) | Q::Longs(_) => Q::Long(i64::NULL),
+            Q::Real(_) | Q::Reals(_) => Q::Real(f32::NULL),
+            Q::Float(_) | Q::Floats(_) => Q::Float(f64::NULL),
+            Q::Char(_) | Q::String(_) => Q::Char(b' '),
+            Q::Symbol(_) | Q::Symbols(_) => Q::Symbol(Symbol::from("")),
+            Q::Timestamp(_) | Q::Timestamps(_) => Q::Timestamp(Timestamp::NULL),
+            Q::Month(_) | Q::Months(_) => Q::Month(Month::NULL),
+            Q::Date(_) | Q::Dates(_) => Q::Date(Date::NULL),
+            Q::Timespan(_) | Q::Timespans(_) => Q::Timespan(Timespan::NULL),
+            Q::Minute(_) | Q::Minutes(_) => Q::Minute(Minute::NULL),
+            Q::Second(_) | Q::Seconds(_) => Q::Second(Second::NULL),
+            Q::List(_) => Q::List(vec![]),
+            Q::Dict(_, values) => values.null_item(),
+            Q::Table(columns, values) => Q::Dict(
+                Box::new(Q::Symbols(columns.clone())),
+                Box::new(Q::List(values.iter().map(Q::null_item).collect())),
+            ),
+            Q::Enum { domain, .. } => Q::enum_null(*domain),
+            Q::Attributed(_, list) => list.null_item(),
+            Q::Null => Q::Null,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn type_compatibility() {
+        let (longs, floats) = (Q::Longs(vec![1, 2]), Q::Floats(vec![1.5]));
+        let symbols = Q::Symbols(vec![Symbol::from("a")]);
+        assert!(longs.same_type(&Q::Longs(vec![3])));
+        assert!(longs.numeric_compatible(&Q::Longs(vec![3])));
+        assert!(!longs.same_type(&floats));
+        assert!(longs.numeric_compatible(&floats));
+        assert!(!longs.same_type(&symbols));
+        assert!(!longs.numeric_compatible(&symbols));
+        assert!(!longs.same_type(&Q::Long(1)));
+    }
 }
@@ -0,0 +1,219 @@
+// Null sentinels of q types, e.g. 0Nh 0Ni 0Nj 0Ne 0n
+use crate::qtype::chrono::{Date, Minute, Month, Second, Timespan, Timestamp};
+use crate::qtype::math::avg;
+use crate::qtype::{Q, QError};
+
+pub trait Nullable: Copy {
+    const NULL: Self;
+
+    fn is_null(&self) -> bool;
+}
+
+macro_rules! impl_nullable_int {
+    ($($t:ty),*) => {
+        $(
+            impl Nullable for $t {
+                const NULL: Self = <$t>::MIN;
+
+                fn is_null(&self) -> bool {
+                    *self == <$t>::MIN
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! impl_nullable_float {
+    ($($t:ty),*) => {
+        $(
+            impl Nullable for $t {
+                const NULL: Self = <$t>::NAN;
+
+                fn is_null(&self) -> bool {
+                    self.is_nan()
+                }
+            }
+        )*
+    };
+}
+
+impl_nullable_int!(i16, i32, i64);
+impl_nullable_float!(f32, f64);
+
+macro_rules! impl_nullable_temporal {
+    ($($t:ty),*) => {
+        $(
+            impl Nullable for $t {
+                const NULL: Self = <$t>::NULL;
+
+                fn is_null(&self) -> bool {
+                    <$t>::is_null(self)
+                }
+            }
+        )*
+    };
+}
+
+impl_nullable_temporal!(Date, Month, Minute, Second, Timespan, Timestamp);
+
+impl Q {
+    /// The number of nulls in `self`, as q's `sum null x`. Booleans and bytes have no null,
+    /// the null symbol is the empty one and the null char a blank. A general list counts its
+    /// null atoms, a dictionary its values and a table every cell.
+    pub fn null_count(&self) -> usize {
+        macro_rules! count {
+            ($($atom:ident / $vector:ident),*) => {
+                match self {
+                    $(
+                        Q::$atom(x) => x.is_null() as usize,
+                        Q::$vector(v) => v.iter().filter(|x| x.is_null()).count(),
+                    )*
+                    Q::Guid(g) => g.is_nil() as usize,
+                    Q::Guids(v) => v.iter().filter(|g| g.is_nil()).count(),
+                    Q::Char(c) => (*c == b' ') as usize,
+                    Q::String(v) => v.iter().filter(|&&c| c == b' ').count(),
+                    Q::Symbol(s) => s.resolve().is_empty() as usize,
+                    Q::Symbols(v) => v.iter().filter(|s| s.resolve().is_empty()).count(),
+                    Q::Boolean(_) | Q::Booleans(_) | Q::Byte(_) | Q::Bytes(_) => 0,
+                    Q::List(items) => items
+                        .iter()
+                        .filter(|item| item.is_atom() && item.null_count() == 1)
+                        .count(),
+                    Q::Dict(_, values) => values.null_count(),
+                    Q::Table(_, values) => values.iter().map(Q::null_count).sum(),
+                    Q::Enum { indices, .. } => indices.null_count(),
+                    Q::Attributed(_, list) => list.null_count(),
+                    Q::Null => 1,
+                }
+            };
+        }
+        count!(
+            Short / Shorts,
+            Int / Ints,
+            Long / Longs,
+            Real / Reals,
+            Float / Floats,
+            Timestamp / Timestamps,
+            Month / Months,
+            Date / Dates,
+            Timespan / Timespans,
+            Minute / Minutes,
+            Second / Seconds
+        )
+    }
+
+    /// Whether `self` has any null, see `null_count`
+    pub fn any_null(&self) -> bool {
+        self.null_count() > 0
+    }
+}
+
+/// How `Q::impute` replaces the nulls of a vector
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImputePolicy {
+    /// The latest non-null item before, as q's `fills`
+    ForwardFill,
+    /// The earliest non-null item after
+    BackFill,
+    /// The average of the non-null items, rounded for integer vectors
+    Mean,
+    /// An atom of the vector's type, as q's `c^x`
+    Constant(Q),
+}
+
+impl Q {
+    /// `self` with its nulls replaced according to `policy`. Nulls with nothing to fill them,
+    /// e.g. leading ones under `ForwardFill`, stay null.
+    pub fn impute(&self, policy: &ImputePolicy) -> Result<Q, QError> {
+        match policy {
+            ImputePolicy::ForwardFill => self.fills(),
+            ImputePolicy::BackFill => self.reverse()?.fills()?.reverse(),
+            ImputePolicy::Mean => self.impute_mean(),
+            ImputePolicy::Constant(atom) => self.impute_constant(atom),
+        }
+    }
+
+    // Helper methods
+    fn impute_mean(&self) -> Result<Q, QError> {
+        macro_rules! impute {
+            ($($vector:ident => $t:ty),*) => {
+                match self.without_attr_ref() {
+                    $(
+                        Q::$vector(v) => {
+                            let mean = avg(v);
+                            let fill = if mean.is_nan() { <$t>::NULL } else { mean.round() as $t };
+                            Q::$vector(fill_nulls(v, fill))
+                        }
+                    )*
+                    Q::Reals(v) => Q::Reals(fill_nulls(v, avg(v) as f32)),
+                    Q::Floats(v) => Q::Floats(fill_nulls(v, avg(v))),
+                    _ => return Err(QError::Type),
+                }
+            };
+        }
+        Ok(impute!(Shorts => i16, Ints => i32, Longs => i64))
+    }
+
+    fn impute_constant(&self, atom: &Q) -> Result<Q, QError> {
+        macro_rules! impute {
+            ($($vector:ident / $atom:ident),*) => {
+                match (self.without_attr_ref(), atom) {
+                    $(
+                        (Q::$vector(v), Q::$atom(c)) => Q::$vector(fill_nulls(v, *c)),
+                    )*
+                    _ => return Err(QError::Type),
+                }
+            };
+        }
+        Ok(impute!(
+            Shorts / Short,
+            Ints / Int,
+            Longs / Long,
+            Reals / Real,
+            Floats / Float,
+            Timestamps / Timestamp,
+            Months / Month,
+            Dates / Date,
+            Timespans / Timespan,
+            Minutes / Minute,
+            Seconds / Second
+        ))
+    }
+}
+
+fn fill_nulls<T: Nullable>(v: &[T], fill: T) -> Vec<T> {
+    v.iter()
+        .map(|x| if x.is_null() { fill } else { *x })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn q(input: &str) -> Q {
+        crate::parse_one(input).unwrap()
+    }
+
+    #[test]
+    fn null_counts() {
+        assert_eq!(q("1 0N 3 0N").null_count(), 2);
+        assert!(q("1 0N 3 0N").any_null());
+        assert_eq!(q("1 2 3").null_count(), 0);
+        assert!(!q("1 2 3").any_null());
+        assert_eq!(q("(0N;`;\"a\")").null_count(), 2);
+        assert_eq!(q("([] a:1 0N; b:``x)").null_count(), 2);
+    }
+
+    #[test]
+    fn impute_policies() {
+        let x = q("0N 2 0N 5 0N");
+        assert_eq!(x.impute(&ImputePolicy::ForwardFill), Ok(q("0N 2 2 5 5")));
+        assert_eq!(x.impute(&ImputePolicy::BackFill), Ok(q("2 2 5 5 0N")));
+        assert_eq!(x.impute(&ImputePolicy::Mean), Ok(q("4 2 4 5 4")));
+        assert_eq!(q("1 0n 2").impute(&ImputePolicy::Mean), Ok(q("1 1.5 2")));
+        let zero = ImputePolicy::Constant(q("0"));
+        assert_eq!(x.impute(&zero), Ok(q("0 2 0 5 0")));
+        assert_eq!(q("1 0n").impute(&zero), Err(QError::Type));
+    }
+}
@@ -0,0 +1,52 @@
+// Directory names of the partitions of an on-disk database, e.g. `db/2024.01.01/`
+use crate::qtype::chrono::Date;
+
+impl Date {
+    /// The name of the directory holding the date's partition, e.g. "2024.01.01"
+    pub fn to_partition_str(self) -> String {
+        self.to_literal()
+    }
+
+    /// The date of a partition directory named like "2024.01.01". A trailing `/` is allowed,
+    /// but null and infinite dates name no partition.
+    pub fn from_partition_str(name: &str) -> Result<Self, String> {
+        let date = name.strip_suffix('/').unwrap_or(name);
+        if date.contains(['N', 'W']) {
+            return Err(format!("'{name}"));
+        }
+        Date::from_literal(date).map_err(|_| format!("'{name}"))
+    }
+}
+
+/// The name of the directory holding an int partition, e.g. "42"
+pub fn int_partition_str(partition: i32) -> String {
+    partition.to_string()
+}
+
+/// The int of a partition directory named by its digits, e.g. "42". A trailing `/` is allowed.
+pub fn parse_int_partition(name: &str) -> Result<i32, String> {
+    let digits = name.strip_suffix('/').unwrap_or(name);
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(format!("'{name}"));
+    }
+    digits.parse().map_err(|_| format!("'{name}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn partition_names_round_trip() {
+        let date = Date::from_partition_str("2024.01.01").unwrap();
+        assert_eq!(date, Date::from_literal("2024.01.01").unwrap());
+        assert_eq!(date.to_partition_str(), "2024.01.01");
+        assert_eq!(Date::from_partition_str("2024.01.01/"), Ok(date));
+        assert!(Date::from_partition_str("0Nd").is_err());
+        assert!(Date::from_partition_str("sym").is_err());
+        assert_eq!(int_partition_str(42), "42");
+        assert_eq!(parse_int_partition("42/"), Ok(42));
+        assert!(parse_int_partition("-1").is_err());
+        assert!(parse_int_partition("").is_err());
+    }
+}
@@ -0,0 +1,314 @@
+// Ordering operations on q vectors
+use crate::qtype::attr::Attr;
+use crate::qtype::{Q, QError};
+use std::cmp::Ordering;
+
+impl Q {
+    /// q's `rank`: the position of each element in the ascending sort order, i.e. `iasc iasc x`.
+    /// Ties are broken by original position, so `rank 3 1 2 1` is `3 0 2 1`.
+    pub fn rank(&self) -> Result<Q, QError> {
+        self.ranks(false)
+    }
+
+    /// Like `rank`, but equal elements share a rank and ranks have no gaps,
+    /// so `rank_dense 3 1 2 1` is `2 0 1 0`.
+    pub fn rank_dense(&self) -> Result<Q, QError> {
+        self.ranks(true)
+    }
+
+    /// q's `asc`: the items of `self` in ascending order, marked with the sorted attribute.
+    /// Nulls sort first and symbols sort by their text.
+    pub fn asc(&self) -> Result<Q, QError> {
+        let sorted = self.sorted(false)?;
+        Ok(Q::Attributed(Attr::Sorted, Box::new(sorted)))
+    }
+
+    /// q's `desc`: the items of `self` in descending order, keeping equal items in their
+    /// original order
+    pub fn desc(&self) -> Result<Q, QError> {
+        self.sorted(true)
+    }
+
+    /// q's `iasc`: the indices that would sort `self` ascending, with equal items kept in
+    /// their original order. Symbols sort by their text, so the result does not depend on the
+    /// order in which they were interned.
+    pub fn iasc(&self) -> Result<Q, QError> {
+        Ok(Q::Longs(
+            self.grade(false)?.into_iter().map(|i| i as i64).collect(),
+        ))
+    }
+
+    /// q's `idesc`: the indices that would sort `self` descending
+    pub fn idesc(&self) -> Result<Q, QError> {
+        Ok(Q::Longs(
+            self.grade(true)?.into_iter().map(|i| i as i64).collect(),
+        ))
+    }
+
+    /// q's `x bin y`: for each of `values`, the index of the last item of the sorted `self`
+    /// that is not greater than it, or -1 if all are greater. An atom gives an atom.
+    pub fn bin(&self, values: &Q) -> Result<Q, QError> {
+        self.search(values, false)
+    }
+
+    /// q's `x binr y`: for each of `values`, the index of the first item of the sorted `self`
+    /// that is not less than it, or the count of `self` if all are less
+    pub fn binr(&self, values: &Q) -> Result<Q, QError> {
+        self.search(values, true)
+    }
+
+    /// The as-of lookup of a single series: for each of `query`, the item of `values` at the
+    /// last of the sorted times `self` not after it, or the null of `values` if there is none.
+    /// `values` pairs item for item with `self`, and an atom query gives an atom.
+    pub fn as_of(&self, values: &Q, query: &Q) -> Result<Q, QError> {
+        if values.is_atom() || self.len() != values.len() {
+            return Err(QError::Length);
+        }
+        let lookup = |i: i64| {
+            usize::try_from(i)
+                .ok()
+                .and_then(|i| values.at(i))
+                .unwrap_or_else(|| values.null_item())
+        };
+        match self.bin(query)? {
+            Q::Long(i) => Ok(lookup(i)),
+            Q::Longs(idx) if idx.is_empty() => Q::empty(values.type_id()),
+            Q::Longs(idx) => Ok(Q::collapse(idx.into_iter().map(lookup).collect())),
+            _ => unreachable!(),
+        }
+    }
+
+    // Helper methods
+    fn search(&self, values: &Q, right: bool) -> Result<Q, QError> {
+        if self.attr() != Some(Attr::Sorted) && !Attr::Sorted.holds(self.without_attr_ref())? {
+            return Err(QError::Fail(Attr::Sorted));
+        }
+        macro_rules! search {
+            ($($vector:ident / $atom:ident => $cmp:expr),*) => {
+                match (self.without_attr_ref(), values.without_attr_ref()) {
+                    $(
+                        (Q::$vector(h), Q::$atom(x)) => Q::Long(position(h, x, right, $cmp)),
+                        (Q::$vector(h), Q::$vector(xs)) => {
+                            Q::Longs(xs.iter().map(|x| position(h, x, right, $cmp)).collect())
+                        }
+                    )*
+                    _ => return Err(QError::Type),
+                }
+            };
+        }
+        Ok(search!(
+            Booleans / Boolean => Ord::cmp,
+            Guids / Guid => Ord::cmp,
+            Bytes / Byte => Ord::cmp,
+            Shorts / Short => Ord::cmp,
+            Ints / Int => Ord::cmp,
+            Longs / Long => Ord::cmp,
+            Reals / Real => |a: &f32, b: &f32| cmp_float(*a as f64, *b as f64),
+            Floats / Float => |a: &f64, b: &f64| cmp_float(*a, *b),
+            String / Char => Ord::cmp,
+            Symbols / Symbol => Ord::cmp,
+            Timestamps / Timestamp => Ord::cmp,
+            Months / Month => Ord::cmp,
+            Dates / Date => Ord::cmp,
+            Timespans / Timespan => Ord::cmp,
+            Minutes / Minute => Ord::cmp,
+            Seconds / Second => Ord::cmp
+        ))
+    }
+
+    fn sorted(&self, descending: bool) -> Result<Q, QError> {
+        let idx = self.grade(descending)?;
+        self.reorder(&idx, |_| false)
+    }
+
+    fn grade(&self, descending: bool) -> Result<Vec<usize>, QError> {
+        macro_rules! grade {
+            ($v:expr, $cmp:expr) => {
+                if descending {
+                    grade_by($v, |a, b| $cmp(b, a))
+                } else {
+                    grade_by($v, $cmp)
+                }
+            };
+        }
+        Ok(match self.without_attr_ref() {
+            Q::Booleans(v) => grade!(v, Ord::cmp),
+            Q::Guids(v) => grade!(v, Ord::cmp),
+            Q::Bytes(v) => grade!(v, Ord::cmp),
+            Q::Shorts(v) => grade!(v, Ord::cmp),
+            Q::Ints(v) => grade!(v, Ord::cmp),
+            Q::Longs(v) => grade!(v, Ord::cmp),
+            Q::Reals(v) => grade!(v, |a: &f32, b: &f32| cmp_float(*a as f64, *b as f64)),
+            Q::Floats(v) => grade!(v, |a: &f64, b: &f64| cmp_float(*a, *b)),
+            Q::String(v) => grade!(v, Ord::cmp),
+            Q::Symbols(v) => grade!(v, Ord::cmp),
+            Q::Timestamps(v) => grade!(v, Ord::cmp),
+            Q::Months(v) => grade!(v, Ord::cmp),
+            Q::Dates(v) => grade!(v, Ord::cmp),
+            Q::Timespans(v) => grade!(v, Ord::cmp),
+            Q::Minutes(v) => grade!(v, Ord::cmp),
+            Q::Seconds(v) => grade!(v, Ord::cmp),
+            _ => return Err(QError::Type),
+        })
+    }
+
+    fn ranks(&self, dense: bool) -> Result<Q, QError> {
+        let ranks = match self.without_attr_ref() {
+            Q::Booleans(v) => ranks_by(v, dense, Ord::cmp),
+            Q::Guids(v) => ranks_by(v, dense, Ord::cmp),
+            Q::Bytes(v) => ranks_by(v, dense, Ord::cmp),
+            Q::Shorts(v) => ranks_by(v, dense, Ord::cmp),
+            Q::Ints(v) => ranks_by(v, dense, Ord::cmp),
+            Q::Longs(v) => ranks_by(v, dense, Ord::cmp),
+            Q::Reals(v) => ranks_by(v, dense, |a, b| cmp_float(*a as f64, *b as f64)),
+            Q::Floats(v) => ranks_by(v, dense, |a, b| cmp_float(*a, *b)),
+            Q::String(v) => ranks_by(v, dense, Ord::cmp),
+            Q::Symbols(v) => ranks_by(v, dense, Ord::cmp),
+            Q::Timestamps(v) => ranks_by(v, dense, Ord::cmp),
+            Q::Months(v) => ranks_by(v, dense, Ord::cmp),
+            Q::Dates(v) => ranks_by(v, dense, Ord::cmp),
+            Q::Timespans(v) => ranks_by(v, dense, Ord::cmp),
+            Q::Minutes(v) => ranks_by(v, dense, Ord::cmp),
+            Q::Seconds(v) => ranks_by(v, dense, Ord::cmp),
+            _ => return Err(QError::Type),
+        };
+        Ok(Q::Longs(ranks))
+    }
+}
+
+/// Stable ascending grade (q's `iasc`) of `v` under `cmp`
+pub(crate) fn grade_by<T>(v: &[T], cmp: impl Fn(&T, &T) -> Ordering) -> Vec<usize> {
+    let mut idx: Vec<usize> = (0..v.len()).collect();
+    idx.sort_by(|&a, &b| cmp(&v[a], &v[b]));
+    idx
+}
+
+/// Total order on floats where null (NaN) sorts before everything, as in q
+pub(crate) fn cmp_float(a: f64, b: f64) -> Ordering {
+    match (a.is_nan(), b.is_nan()) {
+        (true, true) => Ordering::Equal,
+        (true, false) => Ordering::Less,
+        (false, true) => Ordering::Greater,
+        (false, false) => a.partial_cmp(&b).unwrap(),
+    }
+}
+
+/// Binary search in the sorted `v` for `x`, as `bin` (`right` false) or `binr`
+fn position<T>(v: &[T], x: &T, right: bool, cmp: impl Fn(&T, &T) -> Ordering) -> i64 {
+    if right {
+        v.partition_point(|e| cmp(e, x) == Ordering::Less) as i64
+    } else {
+        v.partition_point(|e| cmp(e, x) != Ordering::Greater) as i64 - 1
+    }
+}
+
+fn ranks_by<T>(v: &[T], dense: bool, cmp: impl Fn(&T, &T) -> Ordering) -> Vec<i64> {
+    let grade = grade_by(v, &cmp);
+    let mut ranks = vec![0; v.len()];
+    let mut rank = 0;
+    for (pos, &i) in grade.iter().enumerate() {
+        if !dense {
+            rank = pos as i64;
+        } else if pos > 0 && cmp(&v[grade[pos - 1]], &v[i]) != Ordering::Equal {
+            rank += 1;
+        }
+        ranks[i] = rank;
+    }
+    ranks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::qtype::symbol::Symbol;
+
+    fn q(input: &str) -> Q {
+        crate::parse_one(input).unwrap()
+    }
+
+    #[test]
+    fn rank_is_position_in_sort_order() {
+        assert_eq!(Q::Longs(vec![3, 1, 2]).rank(), Ok(Q::Longs(vec![2, 0, 1])));
+        assert_eq!(
+            Q::Longs(vec![3, 1, 2, 1]).rank(),
+            Ok(Q::Longs(vec![3, 0, 2, 1]))
+        );
+        assert_eq!(
+            Q::Longs(vec![3, 1, 2, 1]).rank_dense(),
+            Ok(Q::Longs(vec![2, 0, 1, 0]))
+        );
+    }
+
+    #[test]
+    fn asc_sorts_and_marks_sorted() {
+        let sorted = Q::Longs(vec![3, 1, 2]).asc().unwrap();
+        assert_eq!(sorted.attr(), Some(Attr::Sorted));
+        assert_eq!(sorted.without_attr_ref(), &Q::Longs(vec![1, 2, 3]));
+        assert_eq!(Q::Longs(vec![3, 1, 2]).desc(), Ok(Q::Longs(vec![3, 2, 1])));
+        // nulls sort first
+        let floats = Q::Floats(vec![2.0, f64::NAN, 1.0]).asc().unwrap();
+        assert_eq!(floats.to_string(), "`s#0n 1 2");
+    }
+
+    #[test]
+    fn bin_finds_the_last_item_not_greater() {
+        let haystack = Q::Longs(vec![2, 4, 6, 8]);
+        assert_eq!(
+            haystack.bin(&Q::Longs(vec![1, 2, 5, 9])),
+            Ok(Q::Longs(vec![-1, 0, 1, 3]))
+        );
+        assert_eq!(haystack.bin(&Q::Long(6)), Ok(Q::Long(2)));
+        assert_eq!(
+            haystack.binr(&Q::Longs(vec![1, 2, 5, 9])),
+            Ok(Q::Longs(vec![0, 0, 2, 4]))
+        );
+        assert_eq!(
+            Q::Longs(vec![3, 1]).bin(&Q::Long(2)),
+            Err(QError::Fail(Attr::Sorted))
+        );
+    }
+
+    #[test]
+    fn as_of_takes_the_latest_value_not_after_each_query() {
+        let times = q("2024.01.01D09:30 2024.01.01D09:31 2024.01.01D09:35");
+        let prices = q("100 101 102f");
+        assert_eq!(
+            times
+                .as_of(
+                    &prices,
+                    &q("2024.01.01D09:30 2024.01.01D09:34 2024.01.01D10:00")
+                )
+                .unwrap(),
+            q("100 101 102f")
+        );
+        assert_eq!(
+            times
+                .as_of(&prices, &q("2024.01.01D09:00"))
+                .unwrap()
+                .to_string(),
+            "0n"
+        );
+        assert_eq!(
+            times.as_of(&q("1 2"), &q("2024.01.01D09:00")),
+            Err(QError::Length)
+        );
+    }
+
+    #[test]
+    fn symbols_sort_by_name_not_interning_order() {
+        // interned in reverse order, so the interner's keys run against the names
+        let names = ["sort-gamma", "sort-beta", "sort-alpha"];
+        let symbols = Q::Symbols(names.iter().map(|&name| Symbol::from(name)).collect());
+        assert!(Symbol::from("sort-alpha") < Symbol::from("sort-gamma"));
+        let sorted: Vec<Symbol> = ["sort-alpha", "sort-beta", "sort-gamma"]
+            .into_iter()
+            .map(Symbol::from)
+            .collect();
+        assert_eq!(
+            symbols.asc(),
+            Ok(Q::Attributed(Attr::Sorted, Box::new(Q::Symbols(sorted))))
+        );
+        assert_eq!(symbols.iasc(), Ok(Q::Longs(vec![2, 1, 0])));
+        assert_eq!(symbols.rank(), Ok(Q::Longs(vec![2, 1, 0])));
+    }
+}
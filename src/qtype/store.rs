@@ -0,0 +1,122 @@
+// Saving q values to disk in the kdb+ IPC format, as q's -8!
+use crate::qtype::Q;
+use crate::qtype::attr::Attr;
+use crate::qtype::ipc::{IpcError, byte_attr, decode_message, encode_message, message_size};
+use miette::Diagnostic;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use thiserror::Error;
+
+#[derive(Diagnostic, Debug, Error)]
+pub enum StoreError {
+    #[error("cannot access the file: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Ipc(#[from] IpcError),
+}
+
+/// The top level of a value written by `save`, as read by `read_header`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QHeader {
+    /// q's `type` of the value
+    pub type_id: i8,
+    /// The count of a list; tables and dictionaries are not counted
+    pub len: Option<usize>,
+    pub attr: Option<Attr>,
+    /// The size of the whole message in bytes
+    pub size: usize,
+}
+
+/// Writes `q` to `path` as one IPC message, replacing any existing file
+pub fn save(path: impl AsRef<Path>, q: &Q) -> Result<(), StoreError> {
+    std::fs::write(path, encode_message(q)?)?;
+    Ok(())
+}
+
+/// Reads back a value written by `save`
+pub fn load(path: impl AsRef<Path>) -> Result<Q, StoreError> {
+    Ok(decode_message(&std::fs::read(path)?)?)
+}
+
+/// q's `hcount`: the size of the file at `path` in bytes
+pub fn hcount(path: impl AsRef<Path>) -> std::io::Result<u64> {
+    Ok(std::fs::metadata(path)?.len())
+}
+
+/// The type, count and attribute of a value written by `save`, reading only the first bytes
+/// of the file
+pub fn read_header(path: impl AsRef<Path>) -> Result<QHeader, StoreError> {
+    // the message header, the type, and for a list its attribute and count
+    let mut bytes = Vec::with_capacity(14);
+    File::open(path)?.take(14).read_to_end(&mut bytes)?;
+    let size = message_size(&bytes)?;
+    let &type_id = bytes.get(8).ok_or(IpcError::Truncated)?;
+    let type_id = type_id as i8;
+    if !(0..=18).contains(&type_id) {
+        return Ok(QHeader {
+            type_id,
+            len: None,
+            attr: None,
+            size,
+        });
+    }
+    let list = bytes.get(9..14).ok_or(IpcError::Truncated)?;
+    let len = i32::from_le_bytes(list[1..].try_into().unwrap());
+    Ok(QHeader {
+        type_id,
+        len: Some(usize::try_from(len).map_err(|_| IpcError::InvalidValue("list count"))?),
+        attr: byte_attr(list[0]),
+        size,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saved_tables_load_back() {
+        let path = std::env::temp_dir().join(format!("rq-store-{}", std::process::id()));
+        let table = crate::parse_one("([] sym:`a`b; px:1.5 2; n:1 2i)").unwrap();
+        save(&path, &table).unwrap();
+        let loaded = load(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.unwrap(), table);
+    }
+
+    #[test]
+    fn headers_describe_saved_values() {
+        let path = std::env::temp_dir().join(format!("rq-header-{}", std::process::id()));
+        let table = crate::parse_one("([] a:1 2 3)").unwrap();
+        save(&path, &table).unwrap();
+        let table_header = read_header(&path);
+        let size = hcount(&path);
+        let sorted = Q::Attributed(Attr::Sorted, Box::new(Q::Longs(vec![1, 2, 3])));
+        save(&path, &sorted).unwrap();
+        let list_header = read_header(&path);
+        std::fs::remove_file(&path).unwrap();
+        let size = size.unwrap();
+        assert_eq!(
+            table_header.unwrap(),
+            QHeader {
+                type_id: 98,
+                len: None,
+                attr: None,
+                size: size as usize,
+            }
+        );
+        assert_eq!(size, encode_message(&table).unwrap().len() as u64);
+        assert_eq!(
+            list_header.unwrap(),
+            QHeader {
+                type_id: 7,
+                len: Some(3),
+                attr: Some(Attr::Sorted),
+                size: 8 + 6 + 3 * 8,
+            }
+        );
+    }
+}
@@ -0,0 +1,233 @@
+// Operations on strings, i.e. char vectors
+use crate::qtype::symbol::Symbol;
+use crate::qtype::{Q, QError};
+
+impl Q {
+    /// q's `sep vs s`: splits the string `self` on every occurrence of `sep`, a char or a
+    /// non-empty string, into a general list of strings. Empty fields are kept, so `"," vs ""`
+    /// is a list of one empty string.
+    pub fn vs(&self, sep: &Q) -> Result<Q, QError> {
+        let Q::String(s) = self.without_attr_ref() else {
+            return Err(QError::Type);
+        };
+        let sep = separator(sep)?;
+        let mut fields = Vec::new();
+        let mut start = 0;
+        let mut i = 0;
+        while i + sep.len() <= s.len() {
+            if s[i..].starts_with(sep) {
+                fields.push(Q::String(s[start..i].to_vec()));
+                i += sep.len();
+                start = i;
+            } else {
+                i += 1;
+            }
+        }
+        fields.push(Q::String(s[start..].to_vec()));
+        Ok(Q::List(fields))
+    }
+
+    /// q's `sep sv strings`: joins a list of strings with `sep`, a char or a string
+    pub fn sv(&self, sep: &Q) -> Result<Q, QError> {
+        let Q::List(strings) = self.without_attr_ref() else {
+            return Err(QError::Type);
+        };
+        let sep = match sep.without_attr_ref() {
+            Q::Char(c) => std::slice::from_ref(c),
+            Q::String(s) => s.as_slice(),
+            _ => return Err(QError::Type),
+        };
+        let mut joined = Vec::new();
+        for (i, string) in strings.iter().enumerate() {
+            let Q::String(s) = string.without_attr_ref() else {
+                return Err(QError::Type);
+            };
+            if i > 0 {
+                joined.extend_from_slice(sep);
+            }
+            joined.extend_from_slice(s);
+        }
+        Ok(Q::String(joined))
+    }
+
+    /// q's `ssr[s;find;replace]`: the string `self` with the occurrences of `find` replaced,
+    /// matching from the left without overlap, so `"aa"` is replaced once in `"aaa"`. An empty
+    /// `find` replaces nothing. Unlike q, `find` is plain text rather than a pattern.
+    pub fn ssr(&self, find: &str, replace: &str) -> Result<Q, QError> {
+        let Q::String(s) = self.without_attr_ref() else {
+            return Err(QError::Type);
+        };
+        let find = find.as_bytes();
+        if find.is_empty() {
+            return Ok(Q::String(s.clone()));
+        }
+        let mut replaced = Vec::with_capacity(s.len());
+        let mut i = 0;
+        while i < s.len() {
+            if s[i..].starts_with(find) {
+                replaced.extend_from_slice(replace.as_bytes());
+                i += find.len();
+            } else {
+                replaced.push(s[i]);
+                i += 1;
+            }
+        }
+        Ok(Q::String(replaced))
+    }
+
+    /// q's `` `$ `` on a list of strings: interns each as a symbol. A single string gives a
+    /// symbol atom.
+    pub fn to_symbols(&self) -> Result<Q, QError> {
+        let symbol = |s: &[u8]| Symbol::from(String::from_utf8_lossy(s).as_ref());
+        match self.without_attr_ref() {
+            Q::String(s) => Ok(Q::Symbol(symbol(s))),
+            Q::List(v) => v
+                .iter()
+                .map(|s| match s {
+                    Q::String(s) => Ok(symbol(s)),
+                    Q::Char(c) => Ok(symbol(&[*c])),
+                    _ => Err(QError::Type),
+                })
+                .collect::<Result<Vec<_>, _>>()
+                .map(Q::Symbols),
+            _ => Err(QError::Type),
+        }
+    }
+
+    /// q's `string` on symbols: the text of each symbol as a string, the inverse of
+    /// `to_symbols`
+    pub fn strings(&self) -> Result<Q, QError> {
+        let string = |s: &Symbol| Q::String(s.resolve().as_bytes().to_vec());
+        match self.without_attr_ref() {
+            Q::Symbol(s) => Ok(string(s)),
+            Q::Symbols(v) => Ok(Q::List(v.iter().map(string).collect())),
+            _ => Err(QError::Type),
+        }
+    }
+
+    /// q's `width$` on each of a list of strings or symbols: pads with blanks or truncates
+    /// to exactly `width` chars. With `left`, the padding goes on the left and truncation
+    /// keeps the last chars, like q's `-width$`.
+    pub fn pad_strings(&self, width: usize, left: bool) -> Result<Q, QError> {
+        let pad = |s: &[u8]| -> Vec<u8> {
+            let blanks = std::iter::repeat_n(b' ', width.saturating_sub(s.len()));
+            if left {
+                blanks
+                    .chain(s[s.len().saturating_sub(width)..].iter().copied())
+                    .collect()
+            } else {
+                s[..s.len().min(width)]
+                    .iter()
+                    .copied()
+                    .chain(blanks)
+                    .collect()
+            }
+        };
+        match self.without_attr_ref() {
+            Q::Symbols(v) => Ok(Q::Symbols(
+                v.iter()
+                    .map(|s| {
+                        Symbol::from(String::from_utf8_lossy(&pad(s.resolve().as_bytes())).as_ref())
+                    })
+                    .collect(),
+            )),
+            Q::List(v) => v
+                .iter()
+                .map(|s| match s.without_attr_ref() {
+                    Q::String(s) => Ok(Q::String(pad(s))),
+                    _ => Err(QError::Type),
+                })
+                .collect(),
+            _ => Err(QError::Type),
+        }
+    }
+}
+
+fn separator(sep: &Q) -> Result<&[u8], QError> {
+    match sep.without_attr_ref() {
+        Q::Char(c) => Ok(std::slice::from_ref(c)),
+        Q::String(s) if s.is_empty() => Err(QError::Domain),
+        Q::String(s) => Ok(s),
+        _ => Err(QError::Type),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::qtype::attr::Attr;
+
+    fn string(text: &str) -> Q {
+        Q::String(text.as_bytes().to_vec())
+    }
+
+    fn strings(texts: &[&str]) -> Q {
+        Q::List(texts.iter().map(|text| string(text)).collect())
+    }
+
+    #[test]
+    fn vs_splits_and_sv_joins() {
+        let comma = Q::Char(b',');
+        assert_eq!(string("a,b,c").vs(&comma), Ok(strings(&["a", "b", "c"])));
+        assert_eq!(strings(&["a", "b", "c"]).sv(&comma), Ok(string("a,b,c")));
+        assert_eq!(string("a,,b,").vs(&comma), Ok(strings(&["a", "", "b", ""])));
+        assert_eq!(string("").vs(&comma), Ok(strings(&[""])));
+        assert_eq!(string("a::b").vs(&string("::")), Ok(strings(&["a", "b"])));
+        assert_eq!(strings(&["a", "b"]).sv(&string("::")), Ok(string("a::b")));
+        let sorted = Q::Attributed(Attr::Sorted, Box::new(string("a,b")));
+        assert_eq!(sorted.vs(&comma), Ok(strings(&["a", "b"])));
+        assert_eq!(
+            string("a::b").vs(&Q::Attributed(Attr::Sorted, Box::new(string("::")))),
+            Ok(strings(&["a", "b"]))
+        );
+        assert_eq!(
+            Q::List(vec![sorted, string("c")]).sv(&comma),
+            Ok(string("a,b,c"))
+        );
+    }
+
+    #[test]
+    fn pad_strings_pads_and_truncates() {
+        let symbols = |names: &[&str]| Q::Symbols(names.iter().map(|&n| Symbol::from(n)).collect());
+        let names = symbols(&["a", "bb", "ccc"]);
+        assert_eq!(
+            names.pad_strings(2, false),
+            Ok(symbols(&["a ", "bb", "cc"]))
+        );
+        assert_eq!(names.pad_strings(2, true), Ok(symbols(&[" a", "bb", "cc"])));
+        assert_eq!(
+            strings(&["a", "bb", "ccc"]).pad_strings(2, false),
+            Ok(strings(&["a ", "bb", "cc"]))
+        );
+        assert_eq!(string("abc").pad_strings(2, false), Err(QError::Type));
+        let sorted = Q::Attributed(Attr::Sorted, Box::new(names));
+        assert_eq!(
+            sorted.pad_strings(2, true),
+            Ok(symbols(&[" a", "bb", "cc"]))
+        );
+    }
+
+    #[test]
+    fn strings_and_symbols_round_trip() {
+        let symbols = Q::Symbols(vec![Symbol::from("a"), Symbol::from("b")]);
+        assert_eq!(strings(&["a", "b"]).to_symbols(), Ok(symbols.clone()));
+        assert_eq!(symbols.strings(), Ok(strings(&["a", "b"])));
+        assert_eq!(string("ab").to_symbols(), Ok(Q::Symbol(Symbol::from("ab"))));
+        assert_eq!(Q::Long(1).strings(), Err(QError::Type));
+    }
+
+    #[test]
+    fn ssr_replaces_without_overlap() {
+        assert_eq!(
+            string("hello world").ssr("o", "0"),
+            Ok(string("hell0 w0rld"))
+        );
+        assert_eq!(string("aaa").ssr("aa", "b"), Ok(string("ba")));
+        assert_eq!(string("abc").ssr("", "x"), Ok(string("abc")));
+        assert_eq!(string("abc").ssr("b", ""), Ok(string("ac")));
+        assert_eq!(
+            Q::Symbol(Symbol::from("abc")).ssr("b", ""),
+            Err(QError::Type)
+        );
+    }
+}
@@ -1,4 +1,5 @@
 use lasso::{Spur, ThreadedRodeo};
+use std::cmp::Ordering;
 use std::fmt;
 use std::sync::LazyLock;
 
@@ -17,6 +18,19 @@ impl Symbol {
     }
 }
 
+// symbols order by their text, not by interning order
+impl Ord for Symbol {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.resolve().cmp(other.resolve())
+    }
+}
+
+impl PartialOrd for Symbol {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 impl fmt::Display for Symbol {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "`{}", self.resolve())
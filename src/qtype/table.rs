@@ -0,0 +1,696 @@
+// Tables, i.e. q's `flip columns!values`
+use crate::qtype::attr::Attr;
+use crate::qtype::display::TYPE_CHARS;
+use crate::qtype::list::{ItemKey, group_items};
+use crate::qtype::symbol::Symbol;
+use crate::qtype::{Q, QError};
+use std::collections::HashMap;
+
+impl Q {
+    /// Builds a table from column names and column values, which must all have the same count
+    pub fn table(columns: Vec<Symbol>, values: Vec<Q>) -> Result<Q, QError> {
+        if columns.len() != values.len() || values.iter().any(Q::is_atom) {
+            return Err(QError::Length);
+        }
+        if let Some(first) = values.first()
+            && values.iter().any(|v| v.len() != first.len())
+        {
+            return Err(QError::Length);
+        }
+        Ok(Q::Table(columns, values))
+    }
+
+    /// q's `flip` between a dictionary of symbols to columns and a table
+    pub fn flip(&self) -> Result<Q, QError> {
+        match self {
+            Q::Dict(keys, values) => {
+                let Q::Symbols(columns) = keys.as_ref() else {
+                    return Err(QError::Type);
+                };
+                let values = (0..values.len())
+                    .map(|i| values.at(i).ok_or(QError::Type))
+                    .collect::<Result<_, _>>()?;
+                Q::table(columns.clone(), values)
+            }
+            Q::Table(columns, values) => Ok(Q::Dict(
+                Box::new(Q::Symbols(columns.clone())),
+                Box::new(Q::List(values.clone())),
+            )),
+            _ => Err(QError::Type),
+        }
+    }
+
+    /// q's `cols`: the column names of a table, or of a keyed table with its key columns first
+    pub fn cols(&self) -> Result<Q, QError> {
+        match self {
+            Q::Table(columns, _) => Ok(Q::Symbols(columns.clone())),
+            Q::Dict(keys, values) => match (keys.as_ref(), values.as_ref()) {
+                (Q::Table(keys, _), Q::Table(values, _)) => {
+                    Ok(Q::Symbols(keys.iter().chain(values).copied().collect()))
+                }
+                _ => Err(QError::Type),
+            },
+            _ => Err(QError::Type),
+        }
+    }
+
+    /// q's `keys`: the key column names of a keyed table, and none for a table
+    pub fn key_cols(&self) -> Result<Q, QError> {
+        match self {
+            Q::Table(..) => Ok(Q::Symbols(vec![])),
+            Q::Dict(keys, _) if matches!(**keys, Q::Table(..)) => keys.cols(),
+            _ => Err(QError::Type),
+        }
+    }
+
+    /// q's `meta`: a keyed table from each column name `c` to its type char `t`, foreign key
+    /// `f` and attribute `a`. A general list of vectors of one type has the uppercase char of
+    /// that type.
+    pub fn meta(&self) -> Result<Q, QError> {
+        let Q::Table(columns, values) = self else {
+            return Err(QError::Type);
+        };
+        let types = values.iter().map(type_char).collect();
+        let attrs = values
+            .iter()
+            .map(|v| {
+                v.attr()
+                    .map_or(Symbol::from(""), |a| Symbol::from(&a.code().to_string()))
+            })
+            .collect();
+        let key = Q::table(vec![Symbol::from("c")], vec![Q::Symbols(columns.clone())])?;
+        let value = Q::table(
+            vec![Symbol::from("t"), Symbol::from("f"), Symbol::from("a")],
+            vec![
+                Q::String(types),
+                Q::Symbols(vec![Symbol::from(""); columns.len()]),
+                Q::Symbols(attrs),
+            ],
+        )?;
+        Ok(Q::Dict(Box::new(key), Box::new(value)))
+    }
+}
+
+impl Q {
+    /// q's `ungroup`: one row per item of the nested columns, i.e. the general lists whose
+    /// items are all lists, with the other columns repeated. Within a row, the nested columns
+    /// must hold lists of equal count.
+    pub fn ungroup(&self) -> Result<Q, QError> {
+        let Q::Table(columns, values) = self else {
+            return Err(QError::Type);
+        };
+        let is_nested =
+            |v: &Q| matches!(v, Q::List(items) if items.iter().all(|item| !item.is_atom()));
+        let rows = self.len();
+        let mut counts: Vec<Option<usize>> = vec![None; rows];
+        for column in values.iter().filter(|v| is_nested(v)) {
+            for (i, count) in counts.iter_mut().enumerate() {
+                let item = column.at(i).ok_or(QError::Length)?;
+                match count {
+                    Some(n) if *n != item.len() => return Err(QError::Length),
+                    _ => *count = Some(item.len()),
+                }
+            }
+        }
+        if counts.iter().all(Option::is_none) {
+            return Ok(self.clone());
+        }
+
+        let idx: Vec<usize> = counts
+            .iter()
+            .enumerate()
+            .flat_map(|(i, n)| std::iter::repeat_n(i, n.unwrap()))
+            .collect();
+        let values = values
+            .iter()
+            .map(|column| match column {
+                Q::List(items) if is_nested(column) => {
+                    let mut flat = Vec::with_capacity(idx.len());
+                    for item in items {
+                        flat.extend(item.items()?);
+                    }
+                    Ok(Q::collapse(flat))
+                }
+                column => column.reorder(&idx, |_| false),
+            })
+            .collect::<Result<_, _>>()?;
+        Q::table(columns.clone(), values)
+    }
+}
+
+impl Q {
+    /// q's `upsert`: `rows` appended to a table, or for a keyed table, replacing the rows
+    /// whose key columns match and appending the others. `rows` is a table, a record or a list
+    /// of records with the same columns, each of the type of the column it adds to unless
+    /// that is a general list.
+    pub fn upsert(&self, rows: &Q) -> Result<Q, QError> {
+        match self {
+            Q::Table(columns, values) => {
+                let rows = conform(columns, values, rows)?;
+                let values = values
+                    .iter()
+                    .zip(rows)
+                    .map(|(column, new)| {
+                        let mut items = column.items()?;
+                        items.extend(new);
+                        Ok(rebuild(column, items))
+                    })
+                    .collect::<Result<_, _>>()?;
+                Q::table(columns.clone(), values)
+            }
+            Q::Dict(keys, values) => {
+                let (Q::Table(key_columns, key_values), Q::Table(value_columns, value_values)) =
+                    (keys.as_ref(), values.as_ref())
+                else {
+                    return Err(QError::Type);
+                };
+                let columns = [key_columns.as_slice(), value_columns].concat();
+                let current = [key_values.as_slice(), value_values].concat();
+                let rows = conform(&columns, &current, rows)?;
+                let mut cells = current
+                    .iter()
+                    .map(Q::items)
+                    .collect::<Result<Vec<_>, _>>()?;
+                // each row's key cells as one item, the current rows followed by the new ones
+                let key_of = |cells: &[Vec<Q>], i: usize| {
+                    Q::List(
+                        cells[..key_columns.len()]
+                            .iter()
+                            .map(|c| c[i].clone())
+                            .collect(),
+                    )
+                };
+                let mut count = self.len();
+                let keys: Vec<Q> = (0..count)
+                    .map(|i| key_of(&cells, i))
+                    .chain((0..rows.first().map_or(0, Vec::len)).map(|j| key_of(&rows, j)))
+                    .collect();
+                let (current_keys, new_keys) = keys.split_at(count);
+                let mut positions: HashMap<ItemKey, usize> = HashMap::with_capacity(keys.len());
+                for (i, key) in current_keys.iter().enumerate() {
+                    positions.entry(ItemKey(key)).or_insert(i);
+                }
+                for (j, key) in new_keys.iter().enumerate() {
+                    match positions.get(&ItemKey(key)) {
+                        Some(&i) => {
+                            for (column, new) in cells.iter_mut().zip(&rows) {
+                                column[i] = new[j].clone();
+                            }
+                        }
+                        None => {
+                            positions.insert(ItemKey(key), count);
+                            count += 1;
+                            for (column, new) in cells.iter_mut().zip(&rows) {
+                                column.push(new[j].clone());
+                            }
+                        }
+                    }
+                }
+                let mut values: Vec<Q> = current
+                    .iter()
+                    .zip(cells)
+                    .map(|(column, items)| rebuild(column, items))
+                    .collect();
+                let value_values = values.split_off(key_columns.len());
+                Ok(Q::Dict(
+                    Box::new(Q::table(key_columns.clone(), values)?),
+                    Box::new(Q::table(value_columns.clone(), value_values)?),
+                ))
+            }
+            _ => Err(QError::Type),
+        }
+    }
+}
+
+/// The cells of `rows`, a table or one or more records, by column of a table with `columns`
+/// and `values`
+fn conform(columns: &[Symbol], values: &[Q], rows: &Q) -> Result<Vec<Vec<Q>>, QError> {
+    let incoming: Vec<Q> = match rows {
+        Q::Table(..) => columns
+            .iter()
+            .map(|name| rows.column(name).cloned().ok_or(QError::Domain))
+            .collect::<Result<_, _>>()?,
+        Q::Dict(..) => return conform(columns, values, &Q::List(vec![rows.clone()])),
+        Q::List(records) => columns
+            .iter()
+            .map(|name| {
+                records
+                    .iter()
+                    .map(|record| {
+                        let Q::Dict(keys, cells) = record else {
+                            return Err(QError::Type);
+                        };
+                        let Q::Symbols(keys) = keys.without_attr_ref() else {
+                            return Err(QError::Type);
+                        };
+                        let k = keys.iter().position(|k| k == name).ok_or(QError::Domain)?;
+                        cells.at(k).ok_or(QError::Length)
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+                    .map(Q::collapse)
+            })
+            .collect::<Result<_, _>>()?,
+        _ => return Err(QError::Type),
+    };
+    // every record must have exactly the columns, or its extra fields would be dropped
+    let conforms = match rows {
+        Q::Table(names, _) => names.len() == columns.len(),
+        Q::List(records) => records.iter().all(|record| record.len() == columns.len()),
+        _ => unreachable!(),
+    };
+    if !conforms {
+        return Err(QError::Length);
+    }
+    values
+        .iter()
+        .zip(incoming)
+        .map(|(column, new)| {
+            if new.is_empty() || column.type_id() == 0 || column.type_id() == new.type_id() {
+                new.items()
+            } else {
+                Err(QError::Type)
+            }
+        })
+        .collect()
+}
+
+/// A column of `items` with the type of `column`, which stays a general list if it was one
+fn rebuild(column: &Q, items: Vec<Q>) -> Q {
+    match column.without_attr_ref() {
+        list if items.is_empty() => list.clone(),
+        Q::List(_) => Q::List(items),
+        _ => Q::collapse(items),
+    }
+}
+
+impl Q {
+    /// q's `aj[cols;self;other]`: for each row of the table `self`, the columns of `other`
+    /// from its latest row matching on all of `cols` but the last, and whose value in the last
+    /// (time) column is at or before the row's. Rows with no such match get nulls, and columns
+    /// of `other` also in `self` take the joined values. The rows of `other` are grouped by
+    /// their keys and each group's times, which must be sorted, are searched with `bin`.
+    pub fn aj(&self, cols: &[Symbol], other: &Q) -> Result<Q, QError> {
+        let (Q::Table(left_columns, _), Q::Table(right_columns, right_values)) = (self, other)
+        else {
+            return Err(QError::Type);
+        };
+        let Some((time, keys)) = cols.split_last() else {
+            return Err(QError::Length);
+        };
+        let column_pair = |name: &Symbol| -> Result<(&Q, &Q), QError> {
+            match (self.column(name), other.column(name)) {
+                (Some(l), Some(r)) => Ok((l, r)),
+                _ => Err(QError::Domain),
+            }
+        };
+        let (left_keys, right_keys): (Vec<&Q>, Vec<&Q>) = keys
+            .iter()
+            .map(column_pair)
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .unzip();
+        let (left_time, right_time) = column_pair(time)?;
+        // the key cells of row `i` as one item
+        let key_of = |columns: &[&Q], i: usize| -> Result<Q, QError> {
+            columns
+                .iter()
+                .map(|c| c.at(i).ok_or(QError::Length))
+                .collect::<Result<_, _>>()
+                .map(Q::List)
+        };
+
+        let right_rows = (0..other.len())
+            .map(|j| key_of(&right_keys, j))
+            .collect::<Result<Vec<_>, _>>()?;
+        let (firsts, group) = group_items(&right_rows);
+        let mut members = vec![Vec::new(); firsts.len()];
+        for (j, &g) in group.iter().enumerate() {
+            members[g].push(j);
+        }
+        let lookup: HashMap<ItemKey, usize> = firsts
+            .iter()
+            .enumerate()
+            .map(|(g, &j)| (ItemKey(&right_rows[j]), g))
+            .collect();
+        let mut queries = vec![Vec::new(); firsts.len()];
+        for i in 0..self.len() {
+            if let Some(&g) = lookup.get(&ItemKey(&key_of(&left_keys, i)?)) {
+                queries[g].push(i);
+            }
+        }
+
+        let mut matches: Vec<Option<usize>> = vec![None; self.len()];
+        for (members, rows) in members.iter().zip(&queries) {
+            if rows.is_empty() {
+                continue;
+            }
+            let times = right_time.reorder(members, |attr| attr == Attr::Sorted)?;
+            let Q::Longs(found) = times.bin(&left_time.reorder(rows, |_| false)?)? else {
+                return Err(QError::Type);
+            };
+            for (&i, k) in rows.iter().zip(found) {
+                matches[i] = usize::try_from(k).ok().map(|k| members[k]);
+            }
+        }
+        let mut columns = left_columns.clone();
+        let Q::Table(_, mut values) = self.clone() else {
+            unreachable!()
+        };
+        for (name, column) in right_columns.iter().zip(right_values) {
+            if cols.contains(name) {
+                continue;
+            }
+            let joined = column.pick_or_null(&matches)?;
+            match columns.iter().position(|c| c == name) {
+                Some(k) => values[k] = joined,
+                None => {
+                    columns.push(*name);
+                    values.push(joined);
+                }
+            }
+        }
+        Q::table(columns, values)
+    }
+
+    /// q's `xcol`: the table with each column named by the first of a pair in `mapping`
+    /// renamed to the second
+    pub fn xcol(&self, mapping: &[(Symbol, Symbol)]) -> Result<Q, QError> {
+        let Q::Table(columns, values) = self else {
+            return Err(QError::Type);
+        };
+        let mut columns = columns.clone();
+        for (from, to) in mapping {
+            let k = columns
+                .iter()
+                .position(|c| c == from)
+                .ok_or(QError::Domain)?;
+            columns[k] = *to;
+        }
+        Q::table(columns, values.clone())
+    }
+
+    /// q's `xcols`: the table with the columns in `order` moved to the front, in that order,
+    /// followed by the others
+    pub fn xcols(&self, order: &[Symbol]) -> Result<Q, QError> {
+        let Q::Table(columns, _) = self else {
+            return Err(QError::Type);
+        };
+        if order.iter().any(|name| !columns.contains(name)) {
+            return Err(QError::Domain);
+        }
+        let rest = columns.iter().filter(|c| !order.contains(c));
+        let columns: Vec<Symbol> = order.iter().chain(rest).copied().collect();
+        let values = columns
+            .iter()
+            .map(|name| self.column(name).unwrap().clone())
+            .collect();
+        Q::table(columns, values)
+    }
+
+    /// The `i`th record of a table, as a dictionary from column names to its cells
+    pub fn row(&self, i: usize) -> Result<Q, QError> {
+        let Q::Table(columns, values) = self else {
+            return Err(QError::Type);
+        };
+        if i >= self.len() {
+            return Err(QError::Length);
+        }
+        let cells = values
+            .iter()
+            .map(|v| v.at(i).ok_or(QError::Length))
+            .collect::<Result<_, _>>()?;
+        Ok(Q::Dict(
+            Box::new(Q::Symbols(columns.clone())),
+            Box::new(Q::collapse(cells)),
+        ))
+    }
+
+    /// The single atom of a one-row one-column table, a dictionary of one key, or a list of
+    /// one item. An atom is its own scalar; anything holding more or fewer cells is a length
+    /// error.
+    pub fn scalar(&self) -> Result<Q, QError> {
+        if self.is_atom() {
+            return Ok(self.clone());
+        }
+        let inner = match self {
+            Q::Table(_, values) if values.len() == 1 => values[0].clone(),
+            Q::Dict(keys, values) if !matches!(**keys, Q::Table(..)) => *values.clone(),
+            Q::Table(..) | Q::Dict(..) => return Err(QError::Length),
+            Q::Null => return Err(QError::Type),
+            _ if self.len() == 1 => self.at(0).ok_or(QError::Type)?,
+            _ => return Err(QError::Length),
+        };
+        if !inner.is_atom() && inner.len() != 1 {
+            return Err(QError::Length);
+        }
+        inner.scalar()
+    }
+
+    /// The column `name` of a table
+    pub(crate) fn column(&self, name: &Symbol) -> Option<&Q> {
+        let Q::Table(columns, values) = self else {
+            return None;
+        };
+        columns.iter().position(|c| c == name).map(|k| &values[k])
+    }
+
+    // Helper methods
+    /// The items of `self` at `idx`, with the typed null where there is no index
+    fn pick_or_null(&self, idx: &[Option<usize>]) -> Result<Q, QError> {
+        if let Some(idx) = idx.iter().copied().collect::<Option<Vec<usize>>>() {
+            return self.reorder(&idx, |_| false);
+        }
+        let null = self.null_item();
+        Ok(Q::collapse(
+            idx.iter()
+                .map(|i| i.map_or(Some(null.clone()), |i| self.at(i)))
+                .collect::<Option<Vec<_>>>()
+                .ok_or(QError::Length)?,
+        ))
+    }
+}
+
+/// Builds a table row by row, checking each cell against the type of its column
+#[derive(Debug, Clone, Default)]
+pub struct TableBuilder {
+    columns: Vec<Symbol>,
+    types: Vec<i8>,
+    cells: Vec<Vec<Q>>,
+}
+
+impl TableBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a column holding atoms of the list type `type_id`, or anything for 0. Columns can
+    /// only be added before the first row.
+    pub fn add_column(&mut self, name: Symbol, type_id: i8) -> Result<(), QError> {
+        Q::empty(type_id)?;
+        if self.cells.first().is_some_and(|cells| !cells.is_empty()) {
+            return Err(QError::Length);
+        }
+        self.columns.push(name);
+        self.types.push(type_id);
+        self.cells.push(Vec::new());
+        Ok(())
+    }
+
+    /// Appends a row, which must have one cell per column, each an atom of its column's type
+    pub fn push_row(&mut self, row: &[Q]) -> Result<(), QError> {
+        if row.len() != self.columns.len() {
+            return Err(QError::Length);
+        }
+        let fits = |(cell, &t): (&Q, &i8)| t == 0 || (cell.is_atom() && cell.type_id() == -t);
+        if !row.iter().zip(&self.types).all(fits) {
+            return Err(QError::Type);
+        }
+        for (cells, cell) in self.cells.iter_mut().zip(row) {
+            cells.push(cell.clone());
+        }
+        Ok(())
+    }
+
+    pub fn build(self) -> Q {
+        let values = self
+            .types
+            .iter()
+            .zip(self.cells)
+            .map(|(&t, cells)| match t {
+                0 => Q::List(cells),
+                _ if cells.is_empty() => Q::empty(t).unwrap(),
+                _ => Q::collapse(cells),
+            })
+            .collect();
+        Q::Table(self.columns, values)
+    }
+}
+
+fn type_char(column: &Q) -> u8 {
+    // only simple vectors have a type char; tables, dictionaries and the like have none
+    let char_of = |q: &Q| {
+        usize::try_from(q.type_id())
+            .ok()
+            .and_then(|i| TYPE_CHARS.as_bytes().get(i).copied())
+            .unwrap_or(b' ')
+    };
+    match column.without_attr_ref() {
+        Q::List(items) => match items.first() {
+            Some(first) if first.type_id() > 0 && items.iter().all(|q| q.same_type(first)) => {
+                char_of(first).to_ascii_uppercase()
+            }
+            _ => b' ',
+        },
+        Q::Enum { .. } => b's',
+        column => char_of(column),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn q(input: &str) -> Q {
+        crate::parse_one(input).unwrap()
+    }
+
+    #[test]
+    fn meta_reports_type_chars_and_attributes() {
+        let t = q("([] a:1 2; b:`x`y)");
+        assert_eq!(t.meta(), Ok(q("([c:`a`b] t:\"js\"; f:``; a:``)")));
+        let sorted = Q::Attributed(Attr::Sorted, Box::new(q("1 2")));
+        let enums = Q::Enum {
+            domain: Symbol::from("d"),
+            indices: Box::new(q("0 1")),
+        };
+        // a general list of enumerations has no type char
+        let nested = Q::List(vec![enums.clone(), enums.clone()]);
+        let t = Q::table(
+            vec![
+                Symbol::from("a"),
+                Symbol::from("e"),
+                Symbol::from("l"),
+                Symbol::from("n"),
+            ],
+            vec![sorted, enums, q("(1 2;3 4)"), nested],
+        )
+        .unwrap();
+        assert_eq!(
+            t.meta(),
+            Ok(q("([c:`a`e`l`n] t:\"jsJ \"; f:````; a:`s```)"))
+        );
+    }
+
+    #[test]
+    fn ungroup_explodes_nested_columns() {
+        let t = q("([] s:`a`b; v:(1 2;3 4); w:(`x`y;`z`w))");
+        assert_eq!(t.ungroup(), Ok(q("([] s:`a`a`b`b; v:1 2 3 4; w:`x`y`z`w)")));
+        let ragged = q("([] s:`a`b; v:(1 2;3 4); w:(`x`y;enlist `z))");
+        assert_eq!(ragged.ungroup(), Err(QError::Length));
+        let flat = q("([] s:`a`b; v:1 2)");
+        assert_eq!(flat.ungroup(), Ok(flat));
+    }
+
+    #[test]
+    fn aj_takes_the_latest_quote_at_or_before_each_trade() {
+        let trades = q("([] sym:`a`b`a`c; time:10:01 10:02 10:05 10:05; px:10 20 30 40)");
+        let quotes = q("([] sym:`a`b`a`b; time:10:00 10:00 10:03 10:04; bid:1 2 3 4)");
+        let cols = [Symbol::from("sym"), Symbol::from("time")];
+        assert_eq!(
+            trades.aj(&cols, &quotes),
+            Ok(q(
+                "([] sym:`a`b`a`c; time:10:01 10:02 10:05 10:05; px:10 20 30 40; bid:1 2 3 0N)"
+            ))
+        );
+        let unsorted = q("([] sym:`a`a; time:10:03 10:00; bid:1 2)");
+        assert_eq!(trades.aj(&cols, &unsorted), Err(QError::Fail(Attr::Sorted)));
+    }
+
+    #[test]
+    fn table_builder_checks_each_row() {
+        let mut builder = TableBuilder::new();
+        builder.add_column(Symbol::from("s"), 11).unwrap();
+        builder.add_column(Symbol::from("v"), 7).unwrap();
+        builder.push_row(&[q("`a"), q("1")]).unwrap();
+        builder.push_row(&[q("`b"), q("2")]).unwrap();
+        assert_eq!(builder.push_row(&[q("`c"), q("3f")]), Err(QError::Type));
+        assert_eq!(builder.push_row(&[q("`c")]), Err(QError::Length));
+        assert_eq!(
+            builder.add_column(Symbol::from("w"), 9),
+            Err(QError::Length)
+        );
+        assert_eq!(builder.build(), q("([] s:`a`b; v:1 2)"));
+        let mut empty = TableBuilder::new();
+        empty.add_column(Symbol::from("f"), 9).unwrap();
+        assert_eq!(empty.build(), q("([] f:`float$())"));
+    }
+
+    #[test]
+    fn xcol_renames_and_xcols_reorders() {
+        let t = q("([] a:1 2; b:3 4; c:5 6)");
+        let renamed = t.xcol(&[(Symbol::from("b"), Symbol::from("x"))]);
+        assert_eq!(renamed, Ok(q("([] a:1 2; x:3 4; c:5 6)")));
+        let missing = t.xcol(&[(Symbol::from("z"), Symbol::from("x"))]);
+        assert_eq!(missing, Err(QError::Domain));
+        let reordered = t.xcols(&[Symbol::from("c"), Symbol::from("a")]);
+        assert_eq!(reordered, Ok(q("([] c:5 6; a:1 2; b:3 4)")));
+        assert_eq!(t.xcols(&[Symbol::from("z")]), Err(QError::Domain));
+    }
+
+    #[test]
+    fn row_is_a_record() {
+        let t = q("([] s:`a`b; v:1 2)");
+        assert_eq!(t.row(1), Ok(q("`s`v!(`b;2)")));
+        assert_eq!(t.row(2), Err(QError::Length));
+        assert_eq!(q("1 2").row(0), Err(QError::Type));
+    }
+
+    #[test]
+    fn upsert_appends_or_replaces_by_key() {
+        let flat = q("([] s:`a`b; v:1 2)");
+        assert_eq!(
+            flat.upsert(&q("([] s:`c`d; v:3 4)")),
+            Ok(q("([] s:`a`b`c`d; v:1 2 3 4)"))
+        );
+        assert_eq!(
+            flat.upsert(&q("`s`v!(`c;3)")),
+            Ok(q("([] s:`a`b`c; v:1 2 3)"))
+        );
+        assert_eq!(flat.upsert(&q("([] s:`c; v:3f)")), Err(QError::Type));
+        assert_eq!(flat.upsert(&q("([] s:`c; w:3)")), Err(QError::Domain));
+        let records = Q::List(vec![q("`s`v!(`c;3)"), q("`s`v`w!(`d;4;5)")]);
+        assert_eq!(flat.upsert(&records), Err(QError::Length));
+        let records = Q::List(vec![q("`s`v!(`c;3)"), q("`v`s!(4;`d)")]);
+        assert_eq!(flat.upsert(&records), Ok(q("([] s:`a`b`c`d; v:1 2 3 4)")));
+        let keyed = q("([k:`a`b] v:1 2)");
+        assert_eq!(
+            keyed.upsert(&q("([] k:`b`c; v:20 30)")),
+            Ok(q("([k:`a`b`c] v:1 20 30)"))
+        );
+    }
+
+    #[test]
+    fn cols_and_key_cols() {
+        let flat = q("([] a:1 2; b:3 4)");
+        assert_eq!(flat.cols(), Ok(q("`a`b")));
+        assert_eq!(flat.key_cols(), Ok(Q::Symbols(vec![])));
+        let keyed = q("([k:1 2] a:3 4; b:5 6)");
+        assert_eq!(keyed.cols(), Ok(q("`k`a`b")));
+        assert_eq!(keyed.key_cols(), Ok(q("enlist `k")));
+        assert_eq!(q("`a`b!1 2").key_cols(), Err(QError::Type));
+    }
+
+    #[test]
+    fn scalar_of_single_cells() {
+        assert_eq!(q("([] a:enlist 7)").scalar(), Ok(q("7")));
+        assert_eq!(q("([] a:1 2)").scalar(), Err(QError::Length));
+        assert_eq!(
+            q("([] a:enlist 1; b:enlist 2)").scalar(),
+            Err(QError::Length)
+        );
+        assert_eq!(q("(enlist `a)!enlist 7").scalar(), Ok(q("7")));
+        assert_eq!(q("enlist `x").scalar(), Ok(q("`x")));
+        assert_eq!(q("7").scalar(), Ok(q("7")));
+        assert_eq!(q("()").scalar(), Err(QError::Length));
+    }
+}
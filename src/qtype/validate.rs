@@ -0,0 +1,202 @@
+// Structural invariants of q values, checked before trusting or shipping them
+use crate::qtype::Q;
+use crate::qtype::attr::Attr;
+use crate::qtype::chrono::{Date, Minute, Month, Second};
+use crate::qtype::null::Nullable;
+use miette::Diagnostic;
+use thiserror::Error;
+
+#[derive(Diagnostic, Debug, Clone, PartialEq, Error)]
+pub enum ValidationError {
+    #[error("dictionary has {keys} keys but {values} values")]
+    DictLength { keys: usize, values: usize },
+
+    #[error("table has {names} column names but {columns} columns")]
+    ColumnCount { names: usize, columns: usize },
+
+    #[error("column `{column}` has {found} rows where the table has {expected}")]
+    ColumnLength {
+        column: String,
+        expected: usize,
+        found: usize,
+    },
+
+    #[error("column `{0}` is an atom")]
+    #[diagnostic(help("every column of a table is a list"))]
+    AtomColumn(String),
+
+    #[error("keyed table has {keys} key rows but {values} value rows")]
+    KeyedTableRows { keys: usize, values: usize },
+
+    #[error("enumeration over `{0}` has indices that are not longs")]
+    EnumIndices(String),
+
+    #[error("enumeration over `{domain}` has the negative index {index}")]
+    EnumIndex { domain: String, index: i64 },
+
+    #[error("value of type {type_id} has the underlying int {raw}, outside the range of its type")]
+    OutOfRange { type_id: i8, raw: i64 },
+
+    #[error("list marked with the {} attribute does not satisfy it", .0.code())]
+    Attribute(Attr),
+}
+
+impl Q {
+    /// Checks the invariants that constructors such as `Q::table` and `Q::dict` maintain, for
+    /// values built by hand or read from elsewhere: keys pair with values, columns share one
+    /// count, enumeration indices are non-negative longs, temporal values are in range and
+    /// attributes hold. Nested values are checked too.
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        match self {
+            Q::Dict(keys, values) => {
+                keys.validate()?;
+                values.validate()?;
+                match (keys.as_ref(), values.as_ref()) {
+                    (Q::Table(..), Q::Table(..)) if keys.len() != values.len() => {
+                        Err(ValidationError::KeyedTableRows {
+                            keys: keys.len(),
+                            values: values.len(),
+                        })
+                    }
+                    _ if !keys.is_atom() && keys.len() != values.len() => {
+                        Err(ValidationError::DictLength {
+                            keys: keys.len(),
+                            values: values.len(),
+                        })
+                    }
+                    _ => Ok(()),
+                }
+            }
+            Q::Table(names, columns) => {
+                if names.len() != columns.len() {
+                    return Err(ValidationError::ColumnCount {
+                        names: names.len(),
+                        columns: columns.len(),
+                    });
+                }
+                let expected = self.len();
+                for (name, column) in names.iter().zip(columns) {
+                    if column.is_atom() {
+                        return Err(ValidationError::AtomColumn(name.resolve().to_string()));
+                    }
+                    if column.len() != expected {
+                        return Err(ValidationError::ColumnLength {
+                            column: name.resolve().to_string(),
+                            expected,
+                            found: column.len(),
+                        });
+                    }
+                    column.validate()?;
+                }
+                Ok(())
+            }
+            Q::Enum { domain, indices } => {
+                let negative = match indices.as_ref() {
+                    Q::Long(i) => Some(*i).filter(|i| *i < 0 && !i.is_null()),
+                    Q::Longs(v) => v.iter().copied().find(|i| *i < 0 && !i.is_null()),
+                    _ => return Err(ValidationError::EnumIndices(domain.resolve().to_string())),
+                };
+                match negative {
+                    Some(index) => Err(ValidationError::EnumIndex {
+                        domain: domain.resolve().to_string(),
+                        index,
+                    }),
+                    None => Ok(()),
+                }
+            }
+            Q::Attributed(attr, list) => {
+                list.validate()?;
+                if list.is_atom() || !attr.holds(list).unwrap_or(false) {
+                    return Err(ValidationError::Attribute(*attr));
+                }
+                Ok(())
+            }
+            Q::List(items) => items.iter().try_for_each(Q::validate),
+            _ => self.validate_temporal(),
+        }
+    }
+
+    // Helper methods
+    fn validate_temporal(&self) -> Result<(), ValidationError> {
+        fn check<T: Copy>(
+            v: &[T],
+            type_id: i8,
+            raw: impl Fn(T) -> i32,
+            from_raw: impl Fn(i32) -> Option<T>,
+        ) -> Result<(), ValidationError> {
+            match v.iter().map(|&x| raw(x)).find(|&x| from_raw(x).is_none()) {
+                Some(raw) => Err(ValidationError::OutOfRange {
+                    type_id,
+                    raw: raw as i64,
+                }),
+                None => Ok(()),
+            }
+        }
+        let type_id = self.type_id();
+        match self {
+            Q::Month(x) => check(&[*x], type_id, Month::to_i32, Month::from_raw),
+            Q::Date(x) => check(&[*x], type_id, Date::to_i32, Date::from_raw),
+            Q::Minute(x) => check(&[*x], type_id, Minute::to_i32, Minute::from_raw),
+            Q::Second(x) => check(&[*x], type_id, Second::to_i32, Second::from_raw),
+            Q::Months(v) => check(v, type_id, Month::to_i32, Month::from_raw),
+            Q::Dates(v) => check(v, type_id, Date::to_i32, Date::from_raw),
+            Q::Minutes(v) => check(v, type_id, Minute::to_i32, Minute::from_raw),
+            Q::Seconds(v) => check(v, type_id, Second::to_i32, Second::from_raw),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::qtype::symbol::Symbol;
+
+    fn q(input: &str) -> Q {
+        crate::parse_one(input).unwrap()
+    }
+
+    #[test]
+    fn well_formed_values_validate() {
+        assert_eq!(q("([] a:1 2; b:`x`y)").validate(), Ok(()));
+        assert_eq!(q("([k:1 2] v:3 4)").validate(), Ok(()));
+        assert_eq!(q("(1 2;`a`b!3 4)").validate(), Ok(()));
+    }
+
+    #[test]
+    fn broken_invariants_are_reported() {
+        let ragged = Q::Table(
+            vec![Symbol::from("a"), Symbol::from("b")],
+            vec![q("1 2"), q("1 2 3")],
+        );
+        assert_eq!(
+            ragged.validate(),
+            Err(ValidationError::ColumnLength {
+                column: "b".to_string(),
+                expected: 2,
+                found: 3,
+            })
+        );
+        let dict = Q::Dict(Box::new(q("`a`b")), Box::new(q("enlist 1")));
+        assert_eq!(
+            dict.validate(),
+            Err(ValidationError::DictLength { keys: 2, values: 1 })
+        );
+        let enums = Q::Enum {
+            domain: Symbol::from("d"),
+            indices: Box::new(q("0 -1")),
+        };
+        assert_eq!(
+            enums.validate(),
+            Err(ValidationError::EnumIndex {
+                domain: "d".to_string(),
+                index: -1,
+            })
+        );
+        let unsorted = Q::Attributed(Attr::Sorted, Box::new(q("2 1")));
+        assert_eq!(
+            unsorted.validate(),
+            Err(ValidationError::Attribute(Attr::Sorted))
+        );
+    }
+}